@@ -0,0 +1,68 @@
+use std::{error::Error, fmt, io};
+
+/// The reasons why a runtime operation can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RuntimeError {
+    /// No runtime is available to run the operation
+    NoRuntime,
+    /// The runtime has been shut down
+    ShutDown,
+    /// Blocking on a future from within an asynchronous context, which would deadlock
+    NestedBlockOn,
+    /// The runtime doesn't support this operation
+    Unsupported(&'static str),
+    /// Too many tasks are already in flight, see `util::Limited`
+    LimitReached,
+    /// No blocking pool is registered with this name, see `Runtime::spawn_blocking_on`
+    UnknownPool,
+    /// There is no shard with this index, see `Sharded::try_spawn_on`
+    UnknownShard,
+    /// An I/O operation the runtime relies on failed, such as starting a thread
+    Io(io::ErrorKind),
+}
+
+impl RuntimeError {
+    /// Check whether the given `std::io::Error` wraps a `RuntimeError`, and get it back if so
+    pub fn from_io_error(err: &io::Error) -> Option<Self> {
+        err.get_ref()?.downcast_ref::<Self>().copied()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRuntime => f.write_str("no runtime available"),
+            Self::ShutDown => f.write_str("the runtime has been shut down"),
+            Self::NestedBlockOn => {
+                f.write_str("cannot block on a future from within an asynchronous context")
+            }
+            Self::Unsupported(op) => write!(f, "unsupported operation: {op}"),
+            Self::LimitReached => f.write_str("too many tasks in flight"),
+            Self::UnknownPool => f.write_str("no blocking pool registered with this name"),
+            Self::UnknownShard => f.write_str("no shard with this index"),
+            Self::Io(kind) => write!(f, "I/O error: {kind}"),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+impl From<io::Error> for RuntimeError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.kind())
+    }
+}
+
+impl From<RuntimeError> for io::Error {
+    fn from(err: RuntimeError) -> Self {
+        let kind = match err {
+            RuntimeError::NoRuntime | RuntimeError::ShutDown => io::ErrorKind::NotConnected,
+            RuntimeError::NestedBlockOn | RuntimeError::LimitReached => io::ErrorKind::WouldBlock,
+            RuntimeError::Unsupported(_) => io::ErrorKind::Unsupported,
+            RuntimeError::UnknownPool | RuntimeError::UnknownShard => io::ErrorKind::NotFound,
+            RuntimeError::Io(kind) => kind,
+        };
+        io::Error::new(kind, err)
+    }
+}
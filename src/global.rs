@@ -0,0 +1,124 @@
+use crate::{
+    DynamicRuntime, Runtime,
+    traits::{AsyncToSocketAddrs, DynRuntime, Executor, Reactor},
+    util::{DynIO, Task},
+};
+use std::{future::Future, io, sync::Arc, sync::OnceLock, time::Duration};
+
+static GLOBAL_RUNTIME: OnceLock<DynamicRuntime> = OnceLock::new();
+
+/// Register the runtime used by `global_runtime` and the free functions of this crate.
+///
+/// This can only be done once, before the global runtime is first used, the runtime is given back
+/// otherwise.
+pub fn set_global_runtime(runtime: Arc<dyn DynRuntime>) -> Result<(), Arc<dyn DynRuntime>> {
+    let mut runtime = Some(runtime);
+    GLOBAL_RUNTIME
+        .get_or_init(|| DynamicRuntime::from_dyn(runtime.take().expect("runtime already taken")));
+    runtime.map_or(Ok(()), Err)
+}
+
+/// Get the process-wide runtime.
+///
+/// If none was registered with `set_global_runtime`, the first enabled backend is used, in this
+/// order: tokio, smol, async-global-executor, builtin, noop.
+pub fn global_runtime() -> &'static DynamicRuntime {
+    GLOBAL_RUNTIME.get_or_init(|| DynamicRuntime::from_dyn(default_runtime()))
+}
+
+fn default_runtime() -> Arc<dyn DynRuntime> {
+    #[cfg(feature = "tokio")]
+    if let Ok(runtime) = Runtime::tokio() {
+        return Arc::new(runtime);
+    }
+    fallback_runtime()
+}
+
+// The first enabled backend after tokio, which may fail to start outside of a tokio context
+#[cfg(feature = "smol")]
+fn fallback_runtime() -> Arc<dyn DynRuntime> {
+    Arc::new(Runtime::smol())
+}
+
+#[cfg(all(
+    feature = "async-global-executor",
+    feature = "async-io",
+    not(feature = "smol")
+))]
+fn fallback_runtime() -> Arc<dyn DynRuntime> {
+    Arc::new(Runtime::async_global_executor())
+}
+
+#[cfg(not(any(
+    feature = "smol",
+    all(feature = "async-global-executor", feature = "async-io")
+)))]
+fn fallback_runtime() -> Arc<dyn DynRuntime> {
+    match Runtime::builtin() {
+        Ok(runtime) => Arc::new(runtime),
+        Err(_) => Arc::new(Runtime::noop()),
+    }
+}
+
+/// Spawn a future on the global runtime, see `global_runtime`
+pub fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+    f: F,
+) -> Task<<DynamicRuntime as Executor>::Task<T>> {
+    global_runtime().spawn(f)
+}
+
+/// Sleep for the given duration on the global runtime, see `global_runtime`
+pub fn sleep(dur: Duration) -> <DynamicRuntime as Reactor>::Sleep {
+    global_runtime().sleep(dur)
+}
+
+/// Connect to a remote host using the global runtime, see `global_runtime`
+pub async fn tcp_connect<A: AsyncToSocketAddrs + Send>(addrs: A) -> io::Result<DynIO> {
+    global_runtime().tcp_connect(addrs).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    #[test]
+    fn default_runtime() {
+        let expected = if cfg!(feature = "tokio") {
+            "tokio"
+        } else if cfg!(feature = "smol") {
+            "smol"
+        } else if cfg!(all(feature = "async-global-executor", feature = "async-io")) {
+            "async-global-executor"
+        } else {
+            "builtin"
+        };
+        assert_eq!(
+            super::default_runtime().capabilities_dyn().executor,
+            expected
+        );
+        assert!(std::ptr::eq(global_runtime(), global_runtime()));
+    }
+
+    #[test]
+    fn set_global_runtime() {
+        // Other tests may have initialized it already, register a working runtime either way
+        let _ = super::set_global_runtime(super::default_runtime());
+        assert!(super::set_global_runtime(Arc::new(Runtime::noop())).is_err());
+        assert_ne!(global_runtime().kind(), "noop");
+    }
+
+    #[test]
+    fn free_functions() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let runtime = global_runtime();
+        assert_eq!(runtime.block_on(spawn(async { 42 })), 42);
+        runtime.block_on(sleep(Duration::from_millis(1)));
+        let connected = runtime.block_on(tcp_connect(addr));
+        match connected {
+            Ok(_) => assert_ne!(runtime.kind(), "builtin"),
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::Unsupported),
+        }
+    }
+}
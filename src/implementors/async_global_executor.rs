@@ -2,8 +2,8 @@
 
 use crate::{
     Runtime,
-    traits::Executor,
-    util::{RuntimeParts, Task},
+    traits::{Capabilities, Executor, LocalExecutor},
+    util::{self, RuntimeParts, Task},
 };
 use std::future::Future;
 
@@ -39,48 +39,108 @@ impl Executor for AsyncGlobalExecutor {
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        AGETask(Some(async_global_executor::spawn(f))).into()
+        AGETask::new(|state| async_global_executor::spawn(util::joinable(f, state))).into()
     }
 
     fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        AGETask(Some(async_global_executor::spawn_blocking(f))).into()
+        AGETask::new(|state| {
+            async_global_executor::spawn_blocking(util::joinable_blocking(f, state))
+        })
+        .into()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            executor: "async-global-executor",
+            local_spawn: true,
+            blocking_pool: true,
+            multi_threaded: true,
+            ..Capabilities::NONE
+        }
+    }
+}
+
+impl LocalExecutor for AsyncGlobalExecutor {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        AGETask::new(|state| async_global_executor::spawn_local(util::joinable(f, state))).into()
     }
 }
 
 mod task {
-    use crate::util::TaskImpl;
+    use crate::util::{AbortHandleImpl, JoinError, TaskImpl, TaskState};
     use async_trait::async_trait;
     use std::{
         future::Future,
         pin::Pin,
+        sync::Arc,
         task::{Context, Poll},
     };
 
     /// An async-global-executor task
     #[derive(Debug)]
-    pub struct AGETask<T: Send + 'static>(pub(super) Option<async_global_executor::Task<T>>);
+    pub struct AGETask<T: Send + 'static> {
+        task: Option<async_global_executor::Task<Result<T, JoinError>>>,
+        state: Arc<TaskState>,
+    }
+
+    impl<T: Send + 'static> AGETask<T> {
+        pub(super) fn new(
+            spawn: impl FnOnce(Arc<TaskState>) -> async_global_executor::Task<Result<T, JoinError>>,
+        ) -> Self {
+            let state = Arc::<TaskState>::default();
+            Self {
+                task: Some(spawn(state.clone())),
+                state,
+            }
+        }
+    }
 
     #[async_trait]
     impl<T: Send + 'static> TaskImpl for AGETask<T> {
         async fn cancel(&mut self) -> Option<T> {
-            self.0.take()?.cancel().await
+            self.task.take()?.cancel().await?.ok()
         }
 
         fn detach(&mut self) {
-            if let Some(task) = self.0.take() {
+            if let Some(task) = self.task.take() {
                 task.detach();
             }
         }
+
+        fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+            match self.task.as_mut() {
+                Some(task) => Pin::new(task).poll(cx),
+                None => Poll::Ready(Err(JoinError::Cancelled)),
+            }
+        }
+
+        fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+            Some(self.state.clone())
+        }
+
+        fn is_finished(&self) -> bool {
+            self.state.is_finished()
+        }
+
+        fn abort(&mut self) {
+            // Let the abort handles know, then drop the task without detaching it to cancel it
+            self.state.abort();
+            self.task.take();
+        }
     }
 
     impl<T: Send + 'static> Future for AGETask<T> {
         type Output = T;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            Pin::new(self.0.as_mut().expect("task canceled")).poll(cx)
+            self.poll_try_join(cx)
+                .map(|res| res.unwrap_or_else(|err| err.resume()))
         }
     }
 }
@@ -100,4 +160,52 @@ mod tests {
         assert_sync(&runtime);
         assert_clone(&runtime);
     }
+
+    #[test]
+    fn spawn_local() {
+        let executor = AsyncGlobalExecutor;
+        let res = executor.block_on(async {
+            let data = std::rc::Rc::new(21);
+            executor.spawn_local(async move { *data * 2 }).await
+        });
+        assert_eq!(res, 42);
+    }
+
+    #[test]
+    fn try_join_panic() {
+        async fn boom() -> u32 {
+            panic!("boom")
+        }
+
+        let executor = AsyncGlobalExecutor;
+        let res = executor.block_on(executor.spawn(boom()).try_join());
+        assert_eq!(res.unwrap_err().to_string(), "task panicked: boom");
+    }
+
+    #[test]
+    fn abort_handle() {
+        let executor = AsyncGlobalExecutor;
+        executor.block_on(async {
+            let task = executor.spawn(std::future::pending::<()>());
+            let handle = task.abort_handle();
+            assert_eq!(handle.id(), task.id());
+            assert!(!handle.is_finished());
+            handle.abort();
+            assert!(task.try_join().await.unwrap_err().is_cancelled());
+            assert!(handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn abort_on_drop() {
+        let executor = AsyncGlobalExecutor;
+        executor.block_on(async {
+            let task = executor.spawn(std::future::pending::<()>()).abort_on_drop();
+            let handle = task.abort_handle();
+            drop(task);
+            while !handle.is_finished() {
+                executor.yield_now().await;
+            }
+        });
+    }
 }
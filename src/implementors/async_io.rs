@@ -1,4 +1,8 @@
-use crate::{sys::AsSysFd, traits::Reactor, util::IOHandle};
+use crate::{
+    sys::AsSysFd,
+    traits::{Capabilities, Reactor},
+    util::IOHandle,
+};
 use async_io::{Async, Timer};
 use futures_core::Stream;
 use futures_io::{AsyncRead, AsyncWrite};
@@ -38,6 +42,15 @@ impl Reactor for AsyncIO {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         Async::<TcpStream>::connect(addr)
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            reactor: "async-io",
+            fd_registration: true,
+            real_timers: true,
+            ..Capabilities::NONE
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,446 @@
+//! Dependency-free implementation of async runtime definition traits, only using std
+
+use crate::{
+    Runtime, RuntimeError,
+    sys::AsSysFd,
+    traits::{Capabilities, Executor, Reactor, RuntimeKit},
+    util::{self, BlockingPool, DummyIO, PoolTask, Task, Timer},
+};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, Weak,
+        atomic::{AtomicU8, Ordering},
+    },
+    task::{Context, Wake, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+// The maximum number of threads running blocking closures
+const MAX_BLOCKING_THREADS: usize = 512;
+
+// The states of a Job: waiting for a wake up, queued, being polled, woken up while being polled
+const IDLE: u8 = 0;
+const SCHEDULED: u8 = 1;
+const RUNNING: u8 = 2;
+const NOTIFIED: u8 = 3;
+
+/// Type alias for the builtin runtime
+pub type BuiltinRuntime = Runtime<Builtin>;
+
+impl BuiltinRuntime {
+    /// Create a new BuiltinRuntime with one worker thread per core
+    pub fn builtin() -> io::Result<Self> {
+        Ok(Self::new(Builtin::new()?))
+    }
+
+    /// Create a new BuiltinRuntime with the given number of worker threads
+    pub fn builtin_with_threads(threads: usize) -> io::Result<Self> {
+        Ok(Self::new(Builtin::with_threads(threads)?))
+    }
+}
+
+thread_local! {
+    static IN_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A small executor only relying on std, for when no third-party runtime is available.
+///
+/// Futures run on a fixed pool of worker threads sharing a single queue, blocking closures on a
+/// separate pool of threads started on demand. If no such thread can be started, `spawn_blocking`
+/// returns a task panicking once awaited, `try_spawn_blocking` returns the error instead.
+///
+/// The reactor only supports timers, driven by a dedicated thread: registering handles and
+/// connecting sockets is unsupported.
+///
+/// The worker threads stop once the last clone is dropped.
+#[derive(Clone)]
+pub struct Builtin {
+    handle: Arc<Handle>,
+}
+
+// Closes the queue once dropped, stopping the workers
+struct Handle {
+    queue: Arc<Queue>,
+    threads: usize,
+    blocking: BlockingPool,
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: VecDeque<Arc<Job>>,
+    closed: bool,
+}
+
+// A spawned future, which reschedules itself when woken up.
+//
+// Only the worker which moved it to RUNNING polls it, so the future lock is never contended.
+struct Job {
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    state: AtomicU8,
+    queue: Weak<Queue>,
+}
+
+impl Builtin {
+    /// Start one worker thread per core
+    pub fn new() -> io::Result<Self> {
+        Self::with_threads(thread::available_parallelism().map_or(1, Into::into))
+    }
+
+    /// Start the given number of worker threads
+    pub fn with_threads(threads: usize) -> io::Result<Self> {
+        let threads = threads.max(1);
+        let queue = Arc::new(Queue {
+            state: Mutex::default(),
+            available: Condvar::new(),
+        });
+        let builtin = Self {
+            handle: Arc::new(Handle {
+                queue: queue.clone(),
+                threads,
+                blocking: BlockingPool::new("async-rs-blocking", MAX_BLOCKING_THREADS, usize::MAX),
+            }),
+        };
+        for index in 0..threads {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("async-rs-worker-{index}"))
+                .spawn(move || queue.run())?;
+        }
+        Ok(builtin)
+    }
+
+    fn submit<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        let (task, delivery) = PoolTask::pending();
+        Arc::new(Job {
+            future: Mutex::new(Some(Box::pin(delivery.run(f)))),
+            state: AtomicU8::new(IDLE),
+            queue: Arc::downgrade(&self.handle.queue),
+        })
+        .schedule();
+        task.into()
+    }
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Jobs pushed once the queue is closed get dropped, canceling their task
+    fn push(&self, job: Arc<Job>) {
+        let mut state = self.lock();
+        if !state.closed {
+            state.jobs.push_back(job);
+            self.available.notify_one();
+        }
+    }
+
+    fn close(&self) {
+        let jobs = {
+            let mut state = self.lock();
+            state.closed = true;
+            self.available.notify_all();
+            std::mem::take(&mut state.jobs)
+        };
+        drop(jobs);
+    }
+
+    // Poll the scheduled jobs until the queue gets closed
+    fn run(&self) {
+        IN_WORKER.with(|worker| worker.set(true));
+        let mut state = self.lock();
+        loop {
+            if state.closed {
+                return;
+            }
+            let Some(job) = state.jobs.pop_front() else {
+                state = self
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+                continue;
+            };
+            drop(state);
+            job.poll();
+            state = self.lock();
+        }
+    }
+}
+
+impl Job {
+    fn schedule(self: &Arc<Self>) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            // Once woken up while running, the worker polling us requeues us, see poll
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+            match self
+                .state
+                .compare_exchange(state, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+        if state == IDLE {
+            self.push();
+        }
+    }
+
+    fn push(self: &Arc<Self>) {
+        if let Some(queue) = self.queue.upgrade() {
+            queue.push(self.clone());
+        }
+    }
+
+    fn poll(self: &Arc<Self>) {
+        self.state.store(RUNNING, Ordering::Release);
+        let mut future = self.future.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(f) = future.as_mut() else {
+            self.state.store(IDLE, Ordering::Release);
+            return;
+        };
+        let waker = Waker::from(self.clone());
+        // Panics are caught by the future itself, see PoolTask
+        if f.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+            *future = None;
+        }
+        drop(future);
+        if self
+            .state
+            .compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            // Woken up while running
+            self.state.store(SCHEDULED, Ordering::Release);
+            self.push();
+        }
+    }
+}
+
+impl Wake for Job {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builtin")
+            .field("threads", &self.handle.threads)
+            .field("blocking", &self.handle.blocking)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RuntimeKit for Builtin {}
+
+impl Executor for Builtin {
+    type Task<T: Send + 'static> = PoolTask<T>;
+
+    fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
+        util::simple_block_on(f)
+    }
+
+    fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.submit(f)
+    }
+
+    fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        // The queue is unbounded, so this can only fail if no thread can be started, in which
+        // case the task panics once awaited. Use try_spawn_blocking to get the error instead.
+        self.handle.blocking.spawn(f).unwrap_or_else(|err| {
+            let (task, delivery) = PoolTask::pending();
+            delivery
+                .run_blocking(move || -> T { panic!("failed to start a blocking thread: {err}") })(
+            );
+            task.into()
+        })
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        // Blocking a worker would stall the tasks queued behind it
+        if IN_WORKER.with(Cell::get) {
+            return Err(RuntimeError::NestedBlockOn);
+        }
+        Ok(self.block_on(f))
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.handle.blocking.spawn(f)
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            executor: "builtin",
+            blocking_pool: true,
+            multi_threaded: self.handle.threads > 1,
+            ..Capabilities::NONE
+        }
+    }
+}
+
+impl Reactor for Builtin {
+    type TcpStream = DummyIO;
+    type Sleep = Timer;
+
+    fn register<H: Read + Write + AsSysFd + Send + 'static>(
+        &self,
+        _socket: H,
+    ) -> io::Result<impl AsyncRead + AsyncWrite + Send + Unpin + 'static> {
+        Err::<DummyIO, _>(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Registering handles is not supported by the builtin runtime",
+        ))
+    }
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        Timer::after(dur)
+    }
+
+    fn interval(&self, dur: Duration) -> impl Stream<Item = Instant> + Send + 'static {
+        util::interval(dur)
+    }
+
+    fn tcp_connect_addr(
+        &self,
+        _addr: SocketAddr,
+    ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
+        async {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Connecting sockets is not supported by the builtin runtime",
+            ))
+        }
+    }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            reactor: "builtin",
+            real_timers: true,
+            ..Capabilities::NONE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_traits() {
+        use crate::util::test::*;
+        let runtime = Runtime::builtin().unwrap();
+        assert_send(&runtime);
+        assert_sync(&runtime);
+        assert_clone(&runtime);
+    }
+
+    #[test]
+    fn spawn() {
+        let runtime = Runtime::builtin_with_threads(2).unwrap();
+        let res = runtime.block_on(async {
+            let tasks = (0..100u64)
+                .map(|i| {
+                    runtime.spawn(async move {
+                        Runtime::<Builtin>::current()
+                            .sleep(Duration::from_millis(i % 5))
+                            .await;
+                        i
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut sum = runtime.spawn_blocking(|| 0).await;
+            for task in tasks {
+                sum += task.await;
+            }
+            sum
+        });
+        assert_eq!(res, 4950);
+        let panicked = runtime.spawn(async { panic!("boom") }).try_join();
+        assert!(runtime.block_on(panicked).is_err_and(|err| err.is_panic()));
+        assert_eq!(runtime.kind(), "builtin");
+    }
+
+    #[test]
+    fn cancel() {
+        let runtime = Runtime::builtin_with_threads(1).unwrap();
+        runtime.block_on(async {
+            let mut task = runtime.spawn(std::future::pending::<()>());
+            let handle = task.abort_handle();
+            assert_eq!(task.cancel().await, None);
+            assert!(task.is_finished() && handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn wake_while_polled() {
+        let runtime = Runtime::builtin_with_threads(4).unwrap();
+        runtime.block_on(async {
+            // Yielding wakes the task up while it is being polled
+            let tasks = (0..20)
+                .map(|_| {
+                    let runtime = runtime.clone();
+                    runtime.clone().spawn(async move {
+                        for _ in 0..100 {
+                            runtime.yield_now().await;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for task in tasks {
+                task.await;
+            }
+        });
+    }
+
+    #[test]
+    fn try_block_on_from_worker() {
+        let builtin = Builtin::with_threads(1).unwrap();
+        let nested = builtin.spawn({
+            let builtin = builtin.clone();
+            async move { builtin.try_block_on(async {}) }
+        });
+        assert_eq!(builtin.block_on(nested), Err(RuntimeError::NestedBlockOn));
+    }
+}
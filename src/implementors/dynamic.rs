@@ -0,0 +1,332 @@
+//! type-erased implementation of async runtime definition traits, picked at runtime
+
+use crate::{
+    Runtime, RuntimeError,
+    sys::AsSysFd,
+    traits::{Capabilities, DynRuntime, Executor, Reactor, RuntimeKit},
+    util::{DynIO, DynOutput, DynSocket, Task},
+};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    future::Future,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use task::DynTask;
+
+/// Type alias for a runtime whose implementation is picked at runtime
+pub type DynamicRuntime = Runtime<Dyn>;
+
+impl DynamicRuntime {
+    /// Create a new DynamicRuntime on top of any DynRuntime implementation
+    pub fn from_dyn(runtime: Arc<dyn DynRuntime>) -> Self {
+        Self::new(Dyn(runtime))
+    }
+
+    /// Detect the runtime from the calling context, falling back to the global runtime.
+    ///
+    /// See `try_detect` for how the runtime is detected. The global runtime can be configured with
+    /// `set_global_runtime`, and is tokio whenever the tokio feature is enabled otherwise: use
+    /// `try_detect` to tell an undetected context apart instead.
+    pub fn detect() -> Self {
+        Self::try_detect().unwrap_or_else(|_| crate::global_runtime().clone())
+    }
+
+    /// Detect the runtime from the calling context.
+    ///
+    /// This returns, in order of preference:
+    /// - a tokio-backed runtime if called from within a tokio context
+    /// - the runtime driving the current task if called from within `block_on` or a task spawned
+    ///   by a `Runtime`, unless disabled with `set_task_context`, as neither smol nor
+    ///   async-global-executor expose their context
+    ///
+    /// Fails with `RuntimeError::NoRuntime` otherwise, in particular for futures driven by smol or
+    /// async-global-executor directly rather than through a `Runtime`.
+    pub fn try_detect() -> Result<Self, RuntimeError> {
+        #[cfg(feature = "tokio")]
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            return Ok(Self::from_dyn(Arc::new(Runtime::tokio_with_handle(handle))));
+        }
+        if let Some(runtime) = Self::try_current() {
+            return Ok(runtime);
+        }
+        crate::runtime::current_dyn()
+            .map(Self::from_dyn)
+            .ok_or(RuntimeError::NoRuntime)
+    }
+}
+
+/// Object implementing async common interfaces on top of a type-erased runtime
+#[derive(Clone, Debug)]
+pub struct Dyn(Arc<dyn DynRuntime>);
+
+impl RuntimeKit for Dyn {}
+
+impl Executor for Dyn {
+    type Task<T: Send + 'static> = DynTask<T>;
+
+    fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
+        let mut output = None;
+        self.0.block_on_dyn(Box::pin(async {
+            output = Some(f.await);
+        }));
+        output.expect("block_on returned before the future completed")
+    }
+
+    fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        DynTask::new(
+            self.0
+                .spawn_dyn(Box::pin(async move { Box::new(f.await) as DynOutput })),
+        )
+        .into()
+    }
+
+    fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        DynTask::new(
+            self.0
+                .spawn_blocking_dyn(Box::new(move || Box::new(f()) as DynOutput)),
+        )
+        .into()
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        let mut output = None;
+        self.0.try_block_on_dyn(Box::pin(async {
+            output = Some(f.await);
+        }))?;
+        Ok(output.expect("block_on returned before the future completed"))
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let task = self
+            .0
+            .try_spawn_dyn(Box::pin(async move { Box::new(f.await) as DynOutput }))?;
+        Ok(DynTask::new(task).into())
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let task = self
+            .0
+            .try_spawn_blocking_dyn(Box::new(move || Box::new(f()) as DynOutput))?;
+        Ok(DynTask::new(task).into())
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let mut f = Some(f);
+        let mut output = None;
+        self.0
+            .block_in_place_dyn(&mut || output = f.take().map(|f| f()));
+        output.expect("block_in_place returned before the closure ran")
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.0.yield_now_dyn()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.0.consume_budget_dyn()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        self.0.capabilities_dyn()
+    }
+}
+
+impl Reactor for Dyn {
+    type TcpStream = DynIO;
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn register<H: Read + Write + AsSysFd + Send + 'static>(
+        &self,
+        socket: H,
+    ) -> io::Result<impl AsyncRead + AsyncWrite + Send + Unpin + 'static> {
+        self.0.register_dyn(DynSocket::new(socket))
+    }
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        self.0.sleep_dyn(dur)
+    }
+
+    fn interval(&self, dur: Duration) -> impl Stream<Item = Instant> + Send + 'static {
+        self.0.interval_dyn(dur)
+    }
+
+    fn tcp_connect_addr(
+        &self,
+        addr: SocketAddr,
+    ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
+        self.0.tcp_connect_addr_dyn(addr)
+    }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        self.0.capabilities_dyn()
+    }
+}
+
+mod task {
+    use crate::util::{AbortHandleImpl, DynOutput, DynTaskImpl, JoinError, TaskImpl};
+    use async_trait::async_trait;
+    use std::{
+        fmt,
+        future::Future,
+        marker::PhantomData,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    /// A type-erased task
+    pub struct DynTask<T: Send + 'static> {
+        inner: Box<dyn DynTaskImpl>,
+        _output: PhantomData<T>,
+    }
+
+    impl<T: Send + 'static> DynTask<T> {
+        pub(super) fn new(inner: Box<dyn DynTaskImpl>) -> Self {
+            Self {
+                inner,
+                _output: PhantomData,
+            }
+        }
+    }
+
+    fn downcast<T: 'static>(output: DynOutput) -> T {
+        *output
+            .downcast()
+            .expect("type-erased task output has an unexpected type")
+    }
+
+    impl<T: Send + 'static> Unpin for DynTask<T> {}
+
+    #[async_trait]
+    impl<T: Send + 'static> TaskImpl for DynTask<T> {
+        async fn cancel(&mut self) -> Option<T> {
+            self.inner.cancel().await.map(downcast)
+        }
+
+        fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+            self.inner.poll_try_join(cx).map(|res| res.map(downcast))
+        }
+
+        fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+            self.inner.abort_handle()
+        }
+
+        fn is_finished(&self) -> bool {
+            self.inner.is_finished()
+        }
+
+        fn abort(&mut self) {
+            self.inner.abort();
+        }
+    }
+
+    impl<T: Send + 'static> Future for DynTask<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            self.poll_try_join(cx)
+                .map(|res| res.unwrap_or_else(|err| err.resume()))
+        }
+    }
+
+    impl<T: Send + 'static> fmt::Debug for DynTask<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("DynTask").finish_non_exhaustive()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_traits() {
+        use crate::util::test::*;
+        let runtime = Runtime::from_dyn(Arc::new(Runtime::noop()));
+        assert_send(&runtime);
+        assert_sync(&runtime);
+        assert_clone(&runtime);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio() {
+        let runtime = Runtime::from_dyn(Arc::new(Runtime::tokio().unwrap()));
+        runtime.block_on(async {
+            runtime.sleep(Duration::from_millis(1)).await;
+            let a = runtime.spawn(async { 12 }).await;
+            let b = runtime.spawn_blocking(|| String::from("30")).await;
+            assert_eq!(a + b.parse::<i32>().unwrap(), 42);
+            let panicked = runtime.spawn(async { panic!("boom") }).try_join().await;
+            assert!(panicked.is_err_and(|err| err.is_panic()));
+            assert_eq!(runtime.block_in_place(|| 3), 3);
+        });
+    }
+
+    #[test]
+    fn detect() {
+        let runtime = Runtime::noop();
+        let detected = runtime.block_on(async { Runtime::detect() });
+        assert_eq!(detected.kind(), "noop");
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn detect_smol() {
+        let runtime = Runtime::smol();
+        let detected = runtime.block_on(async { Runtime::detect() });
+        assert_eq!(detected.kind(), "smol");
+        // No tokio context here even with the tokio feature enabled, the task context is used
+        let spawned = runtime.block_on(runtime.spawn(async {
+            #[cfg(feature = "tokio")]
+            assert!(!crate::util::inside_tokio());
+            Runtime::try_detect().map(|detected| detected.kind())
+        }));
+        assert_eq!(spawned, Ok("smol"));
+        // The raw executor doesn't expose its context
+        let detected = smol::block_on(async { Runtime::try_detect() });
+        assert!(matches!(detected, Err(RuntimeError::NoRuntime)));
+        let detected = smol::block_on(async { Runtime::detect() });
+        assert_eq!(detected.kind(), crate::global_runtime().kind());
+    }
+
+    #[cfg(all(feature = "async-global-executor", feature = "async-io"))]
+    #[test]
+    fn detect_async_global_executor() {
+        let runtime = Runtime::async_global_executor();
+        let detected = runtime.block_on(async { Runtime::detect() });
+        // async-global-executor enters a tokio context when its tokio integration is enabled
+        let expected = if cfg!(feature = "tokio") {
+            "tokio"
+        } else {
+            "async-global-executor"
+        };
+        assert_eq!(detected.kind(), expected);
+        // The raw executor doesn't expose its context
+        if !cfg!(feature = "tokio") {
+            let detected = async_global_executor::block_on(async { Runtime::try_detect() });
+            assert!(matches!(detected, Err(RuntimeError::NoRuntime)));
+        }
+        let detected = async_global_executor::block_on(async { Runtime::detect() });
+        assert_eq!(detected.kind(), crate::global_runtime().kind());
+    }
+}
@@ -78,7 +78,7 @@ impl<T: IntoName + Clone + Send + 'static> ToSocketAddrs for HickoryToSocketAddr
     type Iter = SocketAddrsFromIpAddrs<vec::IntoIter<IpAddr>>;
 
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
-        util::block_on_tokio(self.clone().lookup())
+        util::reentrant_block_on_tokio(self.clone().lookup())
     }
 }
 
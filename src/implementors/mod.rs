@@ -8,6 +8,12 @@ mod async_io;
 #[cfg(feature = "async-io")]
 pub use async_io::*;
 
+mod builtin;
+pub use builtin::*;
+
+mod dynamic;
+pub use dynamic::*;
+
 #[cfg(feature = "hickory-dns")]
 mod hickory;
 #[cfg(feature = "hickory-dns")]
@@ -16,6 +22,11 @@ pub use hickory::*;
 mod noop;
 pub use noop::*;
 
+#[cfg(feature = "sharded")]
+mod sharded;
+#[cfg(feature = "sharded")]
+pub use sharded::*;
+
 #[cfg(feature = "smol")]
 mod smol;
 #[cfg(feature = "smol")]
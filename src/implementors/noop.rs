@@ -1,9 +1,9 @@
 //! noop implementation of async runtime definition traits
 
 use crate::{
-    Runtime,
+    Runtime, RuntimeError,
     sys::AsSysFd,
-    traits::{Executor, Reactor, RuntimeKit},
+    traits::{Capabilities, Executor, LocalExecutor, Reactor, RuntimeKit},
     util::{self, DummyIO, DummyStream, Task},
 };
 use futures_core::Stream;
@@ -47,14 +47,48 @@ impl Executor for Noop {
         &self,
         _f: F,
     ) -> Task<Self::Task<T>> {
-        NTask(PhantomData).into()
+        NTask::new().into()
     }
 
     fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
         &self,
         _f: F,
     ) -> Task<Self::Task<T>> {
-        NTask(PhantomData).into()
+        NTask::new().into()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            executor: "noop",
+            ..Capabilities::NONE
+        }
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        _f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        Err(RuntimeError::Unsupported(
+            "spawning tasks on the noop runtime",
+        ))
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        _f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        Err(RuntimeError::Unsupported(
+            "spawning tasks on the noop runtime",
+        ))
+    }
+}
+
+impl LocalExecutor for Noop {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        _f: F,
+    ) -> Task<Self::Task<T>> {
+        NTask::new().into()
     }
 }
 
@@ -83,26 +117,55 @@ impl Reactor for Noop {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         async { Ok(DummyIO) }
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            reactor: "noop",
+            ..Capabilities::NONE
+        }
+    }
 }
 
 mod task {
-    use crate::util::TaskImpl;
+    use crate::util::{AbortHandleImpl, TaskImpl, TaskState};
     use async_trait::async_trait;
     use std::{
         future::Future,
         marker::PhantomData,
         pin::Pin,
+        sync::Arc,
         task::{Context, Poll},
     };
 
     /// A noop task
+    ///
+    /// The future is dropped right away, so the task counts as finished although it never
+    /// completes.
     #[derive(Debug)]
-    pub struct NTask<T: Send + 'static>(pub(super) PhantomData<T>);
+    pub struct NTask<T: Send + 'static> {
+        state: Arc<TaskState>,
+        _output: PhantomData<T>,
+    }
+
+    impl<T: Send + 'static> NTask<T> {
+        pub(super) fn new() -> Self {
+            let state = Arc::<TaskState>::default();
+            state.finish();
+            Self {
+                state,
+                _output: PhantomData,
+            }
+        }
+    }
 
     impl<T: Send + 'static> Unpin for NTask<T> {}
 
     #[async_trait]
-    impl<T: Send + 'static> TaskImpl for NTask<T> {}
+    impl<T: Send + 'static> TaskImpl for NTask<T> {
+        fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+            Some(self.state.clone())
+        }
+    }
 
     impl<T: Send + 'static> Future for NTask<T> {
         type Output = T;
@@ -125,4 +188,39 @@ mod tests {
         assert_sync(&runtime);
         assert_clone(&runtime);
     }
+
+    #[test]
+    fn capabilities() {
+        let runtime = Runtime::noop();
+        assert_eq!(runtime.kind(), "noop");
+        assert!(!runtime.capabilities().real_timers);
+    }
+
+    #[test]
+    fn finished_tasks() {
+        let runtime = Runtime::noop();
+        let task = runtime.spawn(async {});
+        assert!(task.is_finished());
+        assert!(task.abort_handle().is_finished());
+    }
+
+    #[test]
+    fn blocking_pools() {
+        use crate::{traits::AsyncToSocketAddrs, util::BlockingPool};
+        let runtime = Runtime::noop();
+        assert!(matches!(
+            runtime.spawn_blocking_on("dns", || ()),
+            Err(RuntimeError::UnknownPool)
+        ));
+        runtime.add_blocking_pool(BlockingPool::new("dns", 1, 0));
+        let task = runtime.spawn_blocking_on("dns", || 42).unwrap();
+        assert_eq!(runtime.block_on(task), 42);
+        let addrs = runtime
+            .block_on(runtime.to_socket_addrs("127.0.0.1:80").to_socket_addrs())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, ["127.0.0.1:80".parse().unwrap()]);
+        let stats = runtime.blocking_pool_stats()["dns"];
+        assert_eq!((stats.threads, stats.queued), (1, 0));
+    }
 }
@@ -0,0 +1,622 @@
+//! Thread-per-core implementation of async runtime definition traits, on top of any other one
+
+use crate::{
+    Runtime, RuntimeError,
+    sys::AsSysFd,
+    traits::{Capabilities, Executor, LocalExecutor, Reactor, RuntimeKit},
+    util::{self, JoinError, PoolTask, Task},
+};
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    fmt,
+    future::{self, Future},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, Read, Write},
+    net::SocketAddr,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Type alias for the sharded runtime
+pub type ShardedRuntime<RK> = Runtime<Sharded<RK>>;
+
+impl<RK: RuntimeKit + LocalExecutor> ShardedRuntime<RK> {
+    /// Create a new ShardedRuntime with one shard per core, building each shard's RuntimeKit
+    /// with `build`
+    pub fn sharded(build: impl FnMut() -> io::Result<RK>) -> io::Result<Self> {
+        Ok(Self::new(Sharded::new(build)?))
+    }
+
+    /// Create a new ShardedRuntime with `count` shards, building each shard's RuntimeKit with
+    /// `build`
+    pub fn sharded_with(count: usize, build: impl FnMut() -> io::Result<RK>) -> io::Result<Self> {
+        Ok(Self::new(Sharded::with_shards(count, build)?))
+    }
+
+    /// Spawn a future on the given shard, see `Sharded::spawn_on`
+    pub fn spawn_on<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        self.kit().spawn_local_on(shard, self.in_context(move || f))
+    }
+
+    /// Spawn a non-Send future on the given shard, see `Sharded::spawn_local_on`
+    pub fn spawn_local_on<
+        T: Send + 'static,
+        Fut: Future<Output = T> + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+    >(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        self.kit().spawn_local_on(shard, self.in_context(f))
+    }
+
+    /// Spawn a future on the given shard, see `Sharded::try_spawn_on`
+    pub fn try_spawn_on<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        self.kit()
+            .try_spawn_local_on(shard, self.in_context(move || f))
+    }
+
+    /// Spawn a non-Send future on the given shard, see `Sharded::try_spawn_local_on`
+    pub fn try_spawn_local_on<
+        T: Send + 'static,
+        Fut: Future<Output = T> + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+    >(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        self.kit().try_spawn_local_on(shard, self.in_context(f))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl ShardedRuntime<crate::Tokio> {
+    /// Create a new ShardedRuntime with one current-thread tokio runtime per core
+    pub fn tokio_sharded() -> io::Result<Self> {
+        Self::sharded(current_thread_tokio)
+    }
+
+    /// Create a new ShardedRuntime with `count` current-thread tokio runtimes
+    pub fn tokio_sharded_with(count: usize) -> io::Result<Self> {
+        Self::sharded_with(count, current_thread_tokio)
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn current_thread_tokio() -> io::Result<crate::Tokio> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(crate::Tokio::default().with_runtime(runtime))
+}
+
+thread_local! {
+    // The sharded kit owning the current thread, by address, along with the shard index
+    static CURRENT_SHARD: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Thread-per-core RuntimeKit, running one single-threaded RuntimeKit per shard.
+///
+/// Each shard runs on its own thread, pinned to a core where supported, and drives its kit's
+/// `block_on` for as long as the `Sharded` is alive. Tasks never move between shards: `spawn`
+/// picks one in a round-robin fashion, `spawn_on` targets a given one, and `shard_for_key` can
+/// be used to always route the same key to the same shard. Since tasks stay on their shard,
+/// `spawn_local_on` can run non-Send futures.
+///
+/// Reactor operations (`register`, `sleep`, `tcp_connect_addr`...) and `yield_now` use the kit of
+/// the current shard. From any other thread they fall back to the kit of the first shard, so the
+/// resulting sockets and timers are driven by shard 0 even if they are then awaited elsewhere:
+/// create them from within the shard that is going to use them. Clones share the same shards.
+pub struct Sharded<RK: RuntimeKit + LocalExecutor> {
+    inner: Arc<Inner<RK>>,
+}
+
+struct Inner<RK> {
+    shards: Vec<Arc<Shard<RK>>>,
+    next: AtomicUsize,
+}
+
+type Job<RK> = Box<dyn FnOnce(&RK) + Send>;
+
+struct Shard<RK> {
+    kit: RK,
+    queue: Mutex<Queue<RK>>,
+}
+
+struct Queue<RK> {
+    jobs: VecDeque<Job<RK>>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl<RK: RuntimeKit + LocalExecutor> Sharded<RK> {
+    /// Start one shard per core, building each shard's RuntimeKit with `build`.
+    ///
+    /// The kits are expected to be single-threaded, e.g. current-thread tokio runtimes.
+    pub fn new(build: impl FnMut() -> io::Result<RK>) -> io::Result<Self> {
+        let count = core_affinity::get_core_ids()
+            .map(|cores| cores.len())
+            .filter(|count| *count > 0)
+            .or_else(|| thread::available_parallelism().ok().map(Into::into))
+            .unwrap_or(1);
+        Self::with_shards(count, build)
+    }
+
+    /// Start `count` shards, building each shard's RuntimeKit with `build`.
+    ///
+    /// Shards are pinned to the available cores in order, wrapping around if there are more
+    /// shards than cores.
+    pub fn with_shards(
+        count: usize,
+        mut build: impl FnMut() -> io::Result<RK>,
+    ) -> io::Result<Self> {
+        let shards = (0..count.max(1))
+            .map(|_| {
+                Ok(Arc::new(Shard {
+                    kit: build()?,
+                    queue: Mutex::new(Queue {
+                        jobs: VecDeque::new(),
+                        waker: None,
+                        closed: false,
+                    }),
+                }))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let sharded = Self {
+            inner: Arc::new(Inner {
+                shards,
+                next: AtomicUsize::new(0),
+            }),
+        };
+        let cores = core_affinity::get_core_ids().unwrap_or_default();
+        let owner = sharded.owner();
+        for (index, shard) in sharded.inner.shards.iter().enumerate() {
+            let shard = shard.clone();
+            let core = (!cores.is_empty()).then(|| cores[index % cores.len()]);
+            thread::Builder::new()
+                .name(format!("async-rs-shard-{index}"))
+                .spawn(move || {
+                    if let Some(core) = core {
+                        core_affinity::set_for_current(core);
+                    }
+                    CURRENT_SHARD.with(|current| current.set(Some((owner, index))));
+                    shard.run();
+                })?;
+        }
+        Ok(sharded)
+    }
+
+    /// The number of shards
+    pub fn shards(&self) -> usize {
+        self.inner.shards.len()
+    }
+
+    /// The shard the current thread belongs to, if any
+    pub fn current_shard(&self) -> Option<usize> {
+        CURRENT_SHARD
+            .with(Cell::get)
+            .filter(|(owner, _)| *owner == self.owner())
+            .map(|(_, index)| index)
+    }
+
+    /// Pick the shard for this key, always the same one for a given key
+    pub fn shard_for_key<K: Hash + ?Sized>(&self, key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.shards() as u64) as usize
+    }
+
+    /// Spawn a future on the given shard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such shard, use `try_spawn_on` to get an error instead.
+    pub fn spawn_on<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        self.spawn_local_on(shard, move || f)
+    }
+
+    /// Spawn a non-Send future on the given shard, building it there with `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such shard, use `try_spawn_local_on` to get an error instead.
+    pub fn spawn_local_on<
+        T: Send + 'static,
+        Fut: Future<Output = T> + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+    >(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        self.submit(shard, move |_| f())
+            .unwrap_or_else(|err| panic!("cannot spawn on shard {shard}: {err}"))
+    }
+
+    /// Spawn a future on the given shard.
+    ///
+    /// Fails with `RuntimeError::UnknownShard` if there is no such shard, or with
+    /// `RuntimeError::ShutDown` if it is no longer running.
+    pub fn try_spawn_on<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        self.try_spawn_local_on(shard, move || f)
+    }
+
+    /// Spawn a non-Send future on the given shard, building it there with `f`.
+    ///
+    /// Fails like `try_spawn_on`.
+    pub fn try_spawn_local_on<
+        T: Send + 'static,
+        Fut: Future<Output = T> + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+    >(
+        &self,
+        shard: usize,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        self.check_shard(shard)?;
+        self.submit(shard, move |_| f())
+    }
+
+    // The address of the shared state, identifying this kit in CURRENT_SHARD
+    fn owner(&self) -> usize {
+        Arc::as_ptr(&self.inner).addr()
+    }
+
+    fn next_shard(&self) -> usize {
+        self.inner.next.fetch_add(1, Ordering::Relaxed) % self.shards()
+    }
+
+    // The kit of the current shard, or of the first one from other threads, see the type docs
+    fn local_kit(&self) -> &RK {
+        &self.inner.shards[self.current_shard().unwrap_or(0)].kit
+    }
+
+    // Build a future on the given shard and spawn it there, the task is canceled if the shard is
+    // gone and fails if building the future panics
+    fn submit<T: Send + 'static, Fut: Future<Output = T> + 'static>(
+        &self,
+        shard: usize,
+        make: impl FnOnce(&RK) -> Fut + Send + 'static,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        let shard = self.shard(shard)?;
+        let (task, delivery) = PoolTask::pending();
+        shard.push(Box::new(move |kit: &RK| {
+            // A panicking factory must not take the whole shard down with it
+            match panic::catch_unwind(AssertUnwindSafe(|| make(kit))) {
+                Ok(f) => drop(kit.spawn_local(delivery.run(f))),
+                Err(payload) => delivery.fail(JoinError::Panicked(payload)),
+            }
+        }));
+        Ok(task.into())
+    }
+
+    fn shard(&self, shard: usize) -> Result<&Shard<RK>, RuntimeError> {
+        self.inner
+            .shards
+            .get(shard)
+            .map(Arc::as_ref)
+            .ok_or(RuntimeError::UnknownShard)
+    }
+
+    fn check_shard(&self, shard: usize) -> Result<(), RuntimeError> {
+        if self.shard(shard)?.lock().closed {
+            return Err(RuntimeError::ShutDown);
+        }
+        Ok(())
+    }
+}
+
+impl<RK> Shard<RK> {
+    fn lock(&self) -> MutexGuard<'_, Queue<RK>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Jobs pushed once the shard is closed get dropped, canceling their task
+    fn push(&self, job: Job<RK>) {
+        let mut queue = self.lock();
+        if queue.closed {
+            return;
+        }
+        queue.jobs.push_back(job);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        let mut queue = self.lock();
+        queue.closed = true;
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<RK: RuntimeKit> Shard<RK> {
+    // Run the jobs of this shard until it gets closed
+    fn run(&self) {
+        // Whatever happens to the kit, don't keep accepting jobs nobody will run
+        let _guard = CloseGuard(self);
+        self.kit.block_on(future::poll_fn(|cx| {
+            loop {
+                let mut queue = self.lock();
+                match queue.jobs.pop_front() {
+                    Some(job) => {
+                        drop(queue);
+                        job(&self.kit);
+                    }
+                    None if queue.closed => return Poll::Ready(()),
+                    None => {
+                        queue.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }));
+    }
+}
+
+struct CloseGuard<'a, RK>(&'a Shard<RK>);
+
+impl<RK> Drop for CloseGuard<'_, RK> {
+    fn drop(&mut self) {
+        self.0.close();
+        let jobs = std::mem::take(&mut self.0.lock().jobs);
+        drop(jobs);
+    }
+}
+
+impl<RK> Drop for Inner<RK> {
+    fn drop(&mut self) {
+        for shard in &self.shards {
+            shard.close();
+        }
+    }
+}
+
+impl<RK: RuntimeKit + LocalExecutor> Clone for Sharded<RK> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<RK: RuntimeKit + LocalExecutor> fmt::Debug for Sharded<RK> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sharded")
+            .field("shards", &self.shards())
+            .field("kit", &self.inner.shards[0].kit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<RK: RuntimeKit + LocalExecutor> RuntimeKit for Sharded<RK> {}
+
+impl<RK: RuntimeKit + LocalExecutor> Executor for Sharded<RK> {
+    type Task<T: Send + 'static> = PoolTask<T>;
+
+    fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
+        util::simple_block_on(f)
+    }
+
+    fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.spawn_on(self.next_shard(), f)
+    }
+
+    fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.submit(self.next_shard(), move |kit| kit.spawn_blocking(f))
+            .expect("round-robin shards always exist")
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        // Blocking a shard would stall all of its tasks
+        if self.current_shard().is_some() {
+            return Err(RuntimeError::NestedBlockOn);
+        }
+        Ok(self.block_on(f))
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.try_spawn_on(self.next_shard(), f)
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let shard = self.next_shard();
+        self.check_shard(shard)?;
+        self.submit(shard, move |kit| kit.spawn_blocking(f))
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.local_kit().yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.local_kit().consume_budget()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            executor: "sharded",
+            blocking_pool: self.local_kit().executor_capabilities().blocking_pool,
+            multi_threaded: self.shards() > 1,
+            ..Capabilities::NONE
+        }
+    }
+}
+
+impl<RK: RuntimeKit + LocalExecutor> Reactor for Sharded<RK> {
+    type TcpStream = <RK as Reactor>::TcpStream;
+    type Sleep = <RK as Reactor>::Sleep;
+
+    fn register<H: Read + Write + AsSysFd + Send + 'static>(
+        &self,
+        socket: H,
+    ) -> io::Result<impl AsyncRead + AsyncWrite + Send + Unpin + 'static> {
+        self.local_kit().register(socket)
+    }
+
+    fn sleep(&self, dur: Duration) -> Self::Sleep {
+        self.local_kit().sleep(dur)
+    }
+
+    fn interval(&self, dur: Duration) -> impl Stream<Item = Instant> + Send + 'static {
+        self.local_kit().interval(dur)
+    }
+
+    fn tcp_connect_addr(
+        &self,
+        addr: SocketAddr,
+    ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
+        self.local_kit().tcp_connect_addr(addr)
+    }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        self.local_kit().reactor_capabilities()
+    }
+}
+
+#[cfg(all(test, any(feature = "tokio", feature = "smol")))]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn spawn_on() {
+        let runtime = Runtime::tokio_sharded_with(3).unwrap();
+        let kit = runtime.kit().clone();
+        assert_eq!(kit.shards(), 3);
+        assert_eq!(kit.current_shard(), None);
+        runtime.block_on(async {
+            for shard in 0..3 {
+                let kit = kit.clone();
+                let on = runtime.spawn_on(shard, async move { kit.current_shard() });
+                assert_eq!(on.await, Some(shard));
+            }
+            let local_kit = kit.clone();
+            let local = runtime.spawn_local_on(1, move || {
+                let data = std::rc::Rc::new(21);
+                async move {
+                    runtime_sleep().await;
+                    (*data * 2, local_kit.current_shard())
+                }
+            });
+            assert_eq!(local.await, (42, Some(1)));
+            let spawned = (0..3)
+                .map(|_| {
+                    let kit = kit.clone();
+                    runtime.spawn(async move { kit.current_shard().unwrap() })
+                })
+                .collect::<Vec<_>>();
+            let mut shards = Vec::new();
+            for task in spawned {
+                shards.push(task.await);
+            }
+            shards.sort();
+            assert_eq!(shards, [0, 1, 2]);
+            assert_eq!(runtime.spawn_blocking(|| 42).await, 42);
+        });
+        assert_eq!(kit.shard_for_key("key"), kit.shard_for_key("key"));
+        assert!(kit.shard_for_key(&42) < 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn runtime_sleep() {
+        Runtime::<Sharded<crate::Tokio>>::current()
+            .sleep(Duration::from_millis(1))
+            .await;
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol_shards() {
+        let runtime = Runtime::sharded_with(2, || Ok(crate::Smol)).unwrap();
+        let kit = runtime.kit().clone();
+        assert!(matches!(
+            runtime.try_spawn_on(2, async {}),
+            Err(RuntimeError::UnknownShard)
+        ));
+        runtime.block_on(async {
+            let local_kit = kit.clone();
+            let local = runtime
+                .try_spawn_local_on(1, move || {
+                    let data = std::rc::Rc::new(21);
+                    async move {
+                        local_kit.sleep(Duration::from_millis(1)).await;
+                        (*data * 2, local_kit.current_shard())
+                    }
+                })
+                .unwrap();
+            assert_eq!(local.await, (42, Some(1)));
+            // Off-shard, reactor operations go through the first shard
+            kit.sleep(Duration::from_millis(1)).await;
+            assert_eq!(runtime.spawn_blocking(|| 42).await, 42);
+        });
+    }
+
+    // A panicking factory fails its own task but leaves the shard running
+    fn factory_panic<RK: RuntimeKit + LocalExecutor>(runtime: ShardedRuntime<RK>) {
+        runtime.block_on(async {
+            let panicked = runtime
+                .spawn_local_on(0, || -> std::future::Ready<()> { panic!("boom") })
+                .try_join()
+                .await;
+            assert!(panicked.is_err_and(|err| err.is_panic()));
+            for _ in 0..2 {
+                assert_eq!(runtime.spawn_on(0, async { 42 }).await, 42);
+                assert_eq!(runtime.spawn_blocking(|| 42).await, 42);
+            }
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_factory_panic() {
+        factory_panic(Runtime::tokio_sharded_with(2).unwrap());
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol_factory_panic() {
+        factory_panic(Runtime::sharded_with(2, || Ok(crate::Smol)).unwrap());
+    }
+}
@@ -3,8 +3,8 @@
 use crate::{
     Runtime,
     sys::AsSysFd,
-    traits::{Executor, Reactor, RuntimeKit},
-    util::{IOHandle, Task},
+    traits::{Capabilities, Executor, LocalExecutor, Reactor, RuntimeKit},
+    util::{self, IOHandle, Task},
 };
 use futures_core::Stream;
 use futures_io::{AsyncRead, AsyncWrite};
@@ -13,6 +13,7 @@ use std::{
     future::Future,
     io::{self, Read, Write},
     net::{SocketAddr, TcpStream},
+    rc::Rc,
     time::{Duration, Instant},
 };
 
@@ -28,6 +29,11 @@ impl SmolRuntime {
     }
 }
 
+thread_local! {
+    // smol has no global local executor, so we keep one per thread and drive it from block_on
+    static LOCAL_EXECUTOR: Rc<smol::LocalExecutor<'static>> = Rc::new(smol::LocalExecutor::new());
+}
+
 /// Dummy object implementing async common interfaces on top of smol
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Smol;
@@ -38,21 +44,50 @@ impl Executor for Smol {
     type Task<T: Send + 'static> = STask<T>;
 
     fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
-        smol::block_on(f)
+        let executor = LOCAL_EXECUTOR.with(Rc::clone);
+        smol::block_on(executor.run(f))
     }
 
     fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        STask(Some(smol::spawn(f))).into()
+        STask::new(|state| smol::spawn(util::joinable(f, state))).into()
     }
 
     fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        STask(Some(smol::unblock(f))).into()
+        STask::new(|state| smol::unblock(util::joinable_blocking(f, state))).into()
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        smol::future::yield_now()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            executor: "smol",
+            local_spawn: true,
+            blocking_pool: true,
+            // The global executor runs a single thread unless configured otherwise
+            multi_threaded: std::env::var("SMOL_THREADS")
+                .ok()
+                .and_then(|threads| threads.parse::<usize>().ok())
+                .is_some_and(|threads| threads > 1),
+            ..Capabilities::NONE
+        }
+    }
+}
+
+impl LocalExecutor for Smol {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        STask::new(|state| LOCAL_EXECUTOR.with(|executor| executor.spawn(util::joinable(f, state))))
+            .into()
     }
 }
 
@@ -81,39 +116,86 @@ impl Reactor for Smol {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         Async::<TcpStream>::connect(addr)
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            reactor: "smol",
+            fd_registration: true,
+            real_timers: true,
+            ..Capabilities::NONE
+        }
+    }
 }
 
 mod task {
-    use crate::util::TaskImpl;
+    use crate::util::{AbortHandleImpl, JoinError, TaskImpl, TaskState};
     use async_trait::async_trait;
     use std::{
         future::Future,
         pin::Pin,
+        sync::Arc,
         task::{Context, Poll},
     };
 
     /// A smol task
     #[derive(Debug)]
-    pub struct STask<T: Send + 'static>(pub(super) Option<smol::Task<T>>);
+    pub struct STask<T: Send + 'static> {
+        task: Option<smol::Task<Result<T, JoinError>>>,
+        state: Arc<TaskState>,
+    }
+
+    impl<T: Send + 'static> STask<T> {
+        pub(super) fn new(
+            spawn: impl FnOnce(Arc<TaskState>) -> smol::Task<Result<T, JoinError>>,
+        ) -> Self {
+            let state = Arc::<TaskState>::default();
+            Self {
+                task: Some(spawn(state.clone())),
+                state,
+            }
+        }
+    }
 
     #[async_trait]
     impl<T: Send + 'static> TaskImpl for STask<T> {
         async fn cancel(&mut self) -> Option<T> {
-            self.0.take()?.cancel().await
+            self.task.take()?.cancel().await?.ok()
         }
 
         fn detach(&mut self) {
-            if let Some(task) = self.0.take() {
+            if let Some(task) = self.task.take() {
                 task.detach();
             }
         }
+
+        fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+            match self.task.as_mut() {
+                Some(task) => Pin::new(task).poll(cx),
+                None => Poll::Ready(Err(JoinError::Cancelled)),
+            }
+        }
+
+        fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+            Some(self.state.clone())
+        }
+
+        fn is_finished(&self) -> bool {
+            self.state.is_finished()
+        }
+
+        fn abort(&mut self) {
+            // Let the abort handles know, then drop the task without detaching it to cancel it
+            self.state.abort();
+            self.task.take();
+        }
     }
 
     impl<T: Send + 'static> Future for STask<T> {
         type Output = T;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            Pin::new(self.0.as_mut().expect("task canceled")).poll(cx)
+            self.poll_try_join(cx)
+                .map(|res| res.unwrap_or_else(|err| err.resume()))
         }
     }
 }
@@ -130,4 +212,61 @@ mod tests {
         assert_sync(&runtime);
         assert_clone(&runtime);
     }
+
+    #[test]
+    fn spawn_local() {
+        let runtime = Runtime::smol();
+        let res = runtime.block_on(async {
+            let data = std::rc::Rc::new(21);
+            runtime.spawn_local(async move { *data * 2 }).await
+        });
+        assert_eq!(res, 42);
+    }
+
+    #[test]
+    fn capabilities() {
+        let runtime = Runtime::smol();
+        assert_eq!(runtime.kind(), "smol");
+        if std::env::var_os("SMOL_THREADS").is_none() {
+            assert!(!runtime.capabilities().multi_threaded);
+        }
+    }
+
+    #[test]
+    fn try_join_panic() {
+        async fn boom() -> u32 {
+            panic!("boom")
+        }
+
+        let runtime = Runtime::smol();
+        let res = runtime.block_on(runtime.spawn(boom()).try_join());
+        assert_eq!(res.unwrap_err().to_string(), "task panicked: boom");
+    }
+
+    #[test]
+    fn abort_handle() {
+        let runtime = Runtime::smol();
+        runtime.block_on(async {
+            let task = runtime.spawn(std::future::pending::<()>());
+            let handle = task.abort_handle();
+            assert_eq!(handle.id(), task.id());
+            assert!(!handle.is_finished());
+            handle.abort();
+            assert!(task.try_join().await.unwrap_err().is_cancelled());
+            assert!(handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn abort_on_drop() {
+        let runtime = Runtime::smol();
+        runtime.block_on(async {
+            let task = runtime.spawn(std::future::pending::<()>()).abort_on_drop();
+            let handle = task.abort_handle();
+            drop(task);
+            while !handle.is_finished() {
+                runtime.sleep(Duration::from_millis(1)).await;
+            }
+        });
+    }
 }
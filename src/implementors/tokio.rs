@@ -1,10 +1,13 @@
 //! tokio implementation of async runtime definition traits
 
 use crate::{
-    Runtime,
+    Runtime, RuntimeError,
     sys::AsSysFd,
-    traits::{Executor, Reactor, RuntimeKit},
-    util::Task,
+    traits::{Capabilities, Executor, LocalExecutor, Reactor, RuntimeKit},
+    util::{
+        CancellationToken, ShutdownReport, Task, TaskTracker, TrackGuard, WaitForCancellation,
+        can_block_in_place, in_local_set, in_local_set_scope,
+    },
 };
 use async_compat::{Compat, CompatExt};
 use futures_core::Stream;
@@ -13,12 +16,13 @@ use std::{
     future::Future,
     io::{self, Read, Write},
     net::SocketAddr,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio::{
     net::TcpStream,
     runtime::{EnterGuard, Handle, Runtime as TokioRT},
+    task::{JoinHandle, LocalSet},
     time::Sleep,
 };
 use tokio_stream::{StreamExt, wrappers::IntervalStream};
@@ -48,18 +52,75 @@ impl TokioRuntime {
     pub fn tokio_with_runtime(runtime: TokioRT) -> Self {
         Self::new(Tokio::default().with_runtime(runtime))
     }
+
+    /// Gracefully shut down the owned tokio runtime.
+    ///
+    /// New tasks are rejected, `shutdown_signal` resolves, and the running tasks spawned through
+    /// this runtime get up to `timeout` to complete before being aborted. This blocks the current
+    /// thread while waiting, use `shutdown_background` from asynchronous contexts.
+    ///
+    /// This does nothing if the runtime was created from a handle, as it doesn't own it.
+    pub fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let kit = self.kit();
+        let report = kit
+            .start_shutdown()
+            .map(|(owned, running)| owned.tracker.drain(running, timeout));
+        kit.finish_shutdown(report)
+    }
+
+    /// Gracefully shut down the owned tokio runtime without blocking the current thread, see
+    /// `shutdown`.
+    ///
+    /// If this is awaited from a task spawned through this runtime, that task counts as running
+    /// too.
+    pub async fn shutdown_async(&self, timeout: Duration) -> ShutdownReport {
+        let kit = self.kit();
+        let report = match kit.start_shutdown() {
+            Some((owned, running)) => Some(owned.tracker.drain_async(running, timeout).await),
+            None => None,
+        };
+        kit.finish_shutdown(report)
+    }
+
+    /// Shut down the owned tokio runtime without waiting, aborting all the running tasks.
+    ///
+    /// This does nothing if the runtime was created from a handle, as it doesn't own it.
+    pub fn shutdown_background(&self) -> ShutdownReport {
+        self.shutdown(Duration::ZERO)
+    }
+
+    /// Wait for the owned tokio runtime to start shutting down, so that long-running tasks can
+    /// drain.
+    ///
+    /// This never resolves if the runtime was created from a handle, as it doesn't own it.
+    pub fn shutdown_signal(&self) -> WaitForCancellation {
+        match self.kit().owned.as_ref() {
+            Some(owned) => owned.signal.cancelled(),
+            None => CancellationToken::new().cancelled(),
+        }
+    }
 }
 
 /// Dummy object implementing async common interfaces on top of tokio
 #[derive(Default, Clone, Debug)]
 pub struct Tokio {
     handle: Option<Handle>,
-    runtime: Option<Arc<TokioRT>>,
+    owned: Option<Arc<Owned>>,
+    liveness: Option<Arc<Liveness>>,
+}
+
+// A tokio runtime we own, which we can shut down
+#[derive(Debug)]
+struct Owned {
+    runtime: Mutex<Option<Arc<TokioRT>>>,
+    tracker: Arc<TaskTracker>,
+    signal: CancellationToken,
 }
 
 impl Tokio {
     /// Bind to the tokio Runtime associated to this handle by default.
     pub fn with_handle(mut self, handle: Handle) -> Self {
+        self.liveness = Some(Liveness::track(&handle));
         self.handle = Some(handle);
         self
     }
@@ -67,7 +128,11 @@ impl Tokio {
     /// Bind to the tokio Runtime associated to this handle by default.
     pub fn with_runtime(mut self, runtime: TokioRT) -> Self {
         let handle = runtime.handle().clone();
-        self.runtime = Some(Arc::new(runtime));
+        self.owned = Some(Arc::new(Owned {
+            runtime: Mutex::new(Some(Arc::new(runtime))),
+            tracker: Arc::default(),
+            signal: CancellationToken::new(),
+        }));
         self.with_handle(handle)
     }
 
@@ -80,12 +145,178 @@ impl Tokio {
         self.handle.clone().or_else(|| Handle::try_current().ok())
     }
 
-    fn enter(&self) -> Option<EnterGuard<'_>> {
-        self.runtime
+    fn runtime(&self) -> Option<Arc<TokioRT>> {
+        let owned = self.owned.as_ref()?;
+        owned
+            .runtime
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    // Get the handle, checking that its runtime hasn't been shut down
+    fn live_handle(&self) -> Result<Handle, RuntimeError> {
+        if self
+            .owned
             .as_ref()
-            .map(|r| r.handle())
-            .or(self.handle.as_ref())
-            .map(Handle::enter)
+            .is_some_and(|owned| owned.tracker.is_closed())
+            || self.liveness.as_ref().is_some_and(|l| l.is_stopped())
+        {
+            return Err(RuntimeError::ShutDown);
+        }
+        self.handle().ok_or(RuntimeError::NoRuntime)
+    }
+
+    fn enter(&self) -> Option<EnterGuard<'_>> {
+        self.handle.as_ref().map(Handle::enter)
+    }
+
+    // Spawn a task, tracking it if we own the runtime.
+    // Tasks spawned after shutdown started get aborted right away.
+    fn spawn_tracked<T>(
+        &self,
+        blocking: bool,
+        spawn: impl FnOnce(Option<TrackGuard>) -> JoinHandle<T>,
+    ) -> JoinHandle<T> {
+        let Some(owned) = self.owned.as_ref() else {
+            return spawn(None);
+        };
+        let Some(guard) = owned.tracker.track(blocking) else {
+            let task = spawn(None);
+            task.abort();
+            return task;
+        };
+        let key = guard.key();
+        let task = spawn(Some(guard));
+        owned
+            .tracker
+            .set_abort_handle(key, Arc::new(task.abort_handle()));
+        task
+    }
+
+    fn spawn_on<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        handle: Option<Handle>,
+        f: F,
+    ) -> JoinHandle<T> {
+        self.spawn_tracked(false, |guard| {
+            let f = async move {
+                let _guard = guard;
+                f.await
+            };
+            match handle {
+                Some(handle) => handle.spawn(f),
+                None => tokio::task::spawn(f),
+            }
+        })
+    }
+
+    fn spawn_blocking_on<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        handle: Option<Handle>,
+        f: F,
+    ) -> JoinHandle<T> {
+        self.spawn_tracked(true, |guard| {
+            let f = move || {
+                let _guard = guard;
+                f()
+            };
+            match handle {
+                Some(handle) => handle.spawn_blocking(f),
+                None => tokio::task::spawn_blocking(f),
+            }
+        })
+    }
+
+    // Stop accepting tasks and signal the running ones, returning how many of them there are
+    fn start_shutdown(&self) -> Option<(&Owned, usize)> {
+        let owned = self.owned.as_ref()?;
+        let running = owned.tracker.close();
+        owned.signal.cancel();
+        Some((owned, running))
+    }
+
+    fn finish_shutdown(&self, report: Option<ShutdownReport>) -> ShutdownReport {
+        let Some(owned) = self.owned.as_ref() else {
+            return ShutdownReport::default();
+        };
+        let runtime = owned
+            .runtime
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        // If someone is still inside block_on, the runtime gets dropped once they're done
+        if let Some(runtime) = runtime.and_then(|runtime| Arc::try_unwrap(runtime).ok()) {
+            runtime.shutdown_background();
+        }
+        report.unwrap_or_default()
+    }
+}
+
+// Tracks whether the runtime behind a handle has been shut down, through a task which tokio drops
+// when shutting down. The task completes once we get dropped.
+#[derive(Debug)]
+struct Liveness {
+    stopped: CancellationToken,
+    released: CancellationToken,
+}
+
+impl Liveness {
+    fn track(handle: &Handle) -> Arc<Self> {
+        let liveness = Self {
+            stopped: CancellationToken::new(),
+            released: CancellationToken::new(),
+        };
+        let guard = StopGuard(liveness.stopped.clone());
+        let released = liveness.released.cancelled();
+        handle.spawn(async move {
+            let _guard = guard;
+            released.await;
+        });
+        Arc::new(liveness)
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.is_cancelled()
+    }
+}
+
+impl Drop for Liveness {
+    fn drop(&mut self) {
+        self.released.cancel();
+    }
+}
+
+// Marks the runtime as shut down once dropped
+struct StopGuard(CancellationToken);
+
+impl Drop for StopGuard {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        if let Some(runtime) = self
+            .runtime
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            release(runtime);
+        }
+    }
+}
+
+// Drop our reference to an owned tokio runtime.
+// Tokio panics when a runtime gets dropped from an asynchronous context, so if this was the last
+// reference, we drop it from a background thread instead.
+fn release(runtime: Arc<TokioRT>) {
+    if let Ok(runtime) = Arc::try_unwrap(runtime)
+        && Handle::try_current().is_ok()
+    {
+        std::thread::spawn(move || drop(runtime));
     }
 }
 
@@ -95,8 +326,13 @@ impl Executor for Tokio {
     type Task<T: Send + 'static> = TTask<T>;
 
     fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
-        if let Some(runtime) = self.runtime.as_ref() {
-            runtime.block_on(f)
+        // Run inside a LocalSet so that spawn_local can be used from within block_on
+        let local = LocalSet::new();
+        let f = local.run_until(in_local_set_scope(f));
+        if let Some(runtime) = self.runtime() {
+            let res = runtime.block_on(f);
+            release(runtime);
+            res
         } else if let Some(handle) = self.handle() {
             handle.block_on(f)
         } else {
@@ -110,24 +346,94 @@ impl Executor for Tokio {
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        TTask(Some(if let Some(handle) = self.handle() {
-            handle.spawn(f)
-        } else {
-            tokio::task::spawn(f)
-        }))
-        .into()
+        TTask::new(self.spawn_on(self.handle(), f)).into()
     }
 
     fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        TTask(Some(if let Some(handle) = self.handle() {
-            handle.spawn_blocking(f)
+        TTask::new(self.spawn_blocking_on(self.handle(), f)).into()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        let multi_threaded = self.handle().is_some_and(|handle| {
+            handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread
+        });
+        Capabilities {
+            executor: "tokio",
+            local_spawn: true,
+            blocking_pool: true,
+            multi_threaded,
+            ..Capabilities::NONE
+        }
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        if tokio::task::try_id().is_some() {
+            return Err(RuntimeError::NestedBlockOn);
+        }
+        if self.runtime().is_none() {
+            self.live_handle()?;
+        }
+        Ok(self.block_on(f))
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let handle = self.live_handle()?;
+        Ok(TTask::new(self.spawn_on(Some(handle), f)).into())
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let handle = self.live_handle()?;
+        Ok(TTask::new(self.spawn_blocking_on(Some(handle), f)).into())
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        // block_in_place panics on current-thread runtimes, run inline there
+        if Handle::try_current().is_ok_and(|handle| can_block_in_place(&handle)) {
+            tokio::task::block_in_place(f)
         } else {
-            tokio::task::spawn_blocking(f)
-        }))
-        .into()
+            f()
+        }
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        tokio::task::yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        tokio::task::consume_budget()
+    }
+}
+
+impl LocalExecutor for Tokio {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.try_spawn_local(f).expect(
+            "tokio can only spawn local tasks from within block_on, use spawn for tasks started elsewhere",
+        )
+    }
+
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        // tokio panics when spawning local tasks outside of a LocalSet
+        if !in_local_set() {
+            return Err(RuntimeError::Unsupported(
+                "spawning local tasks outside of block_on",
+            ));
+        }
+        Ok(TTask::new(tokio::task::spawn_local(in_local_set_scope(f))).into())
     }
 }
 
@@ -170,39 +476,95 @@ impl Reactor for Tokio {
         let _enter = self.enter();
         async move { Ok(TcpStream::connect(addr).await?.compat()) }
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities {
+            reactor: "tokio",
+            fd_registration: cfg!(unix),
+            real_timers: true,
+            async_dns: cfg!(feature = "hickory-dns"),
+            ..Capabilities::NONE
+        }
+    }
 }
 
 mod task {
-    use crate::util::TaskImpl;
+    use crate::util::{AbortHandleImpl, JoinError, TaskImpl};
     use async_trait::async_trait;
     use std::{
         future::Future,
         pin::Pin,
+        sync::Arc,
         task::{Context, Poll},
     };
+    use tokio::task::{AbortHandle, JoinHandle};
 
     /// A tokio task
     #[derive(Debug)]
-    pub struct TTask<T: Send + 'static>(pub(super) Option<tokio::task::JoinHandle<T>>);
+    pub struct TTask<T: Send + 'static> {
+        task: Option<JoinHandle<T>>,
+        // Kept once the JoinHandle is gone so that we still know when the task finishes
+        handle: AbortHandle,
+    }
+
+    impl<T: Send + 'static> TTask<T> {
+        pub(super) fn new(task: JoinHandle<T>) -> Self {
+            Self {
+                handle: task.abort_handle(),
+                task: Some(task),
+            }
+        }
+    }
 
     #[async_trait]
     impl<T: Send + 'static> TaskImpl for TTask<T> {
         async fn cancel(&mut self) -> Option<T> {
-            let task = self.0.take()?;
+            let task = self.task.take()?;
             task.abort();
             task.await.ok()
         }
+
+        fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+            let Some(task) = self.task.as_mut() else {
+                return Poll::Ready(Err(JoinError::Cancelled));
+            };
+            Pin::new(task)
+                .poll(cx)
+                .map_err(|err| match err.try_into_panic() {
+                    Ok(payload) => JoinError::Panicked(payload),
+                    Err(_) => JoinError::Cancelled,
+                })
+        }
+
+        fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+            Some(Arc::new(self.handle.clone()))
+        }
+
+        fn is_finished(&self) -> bool {
+            self.handle.is_finished()
+        }
+
+        fn abort(&mut self) {
+            self.handle.abort();
+        }
+    }
+
+    impl AbortHandleImpl for AbortHandle {
+        fn abort(&self) {
+            self.abort();
+        }
+
+        fn is_finished(&self) -> bool {
+            self.is_finished()
+        }
     }
 
     impl<T: Send + 'static> Future for TTask<T> {
         type Output = T;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            let task = self.0.as_mut().expect("task has been canceled");
-            match Pin::new(task).poll(cx) {
-                Poll::Pending => Poll::Pending,
-                Poll::Ready(res) => Poll::Ready(res.expect("task has been canceled")),
-            }
+            self.poll_try_join(cx)
+                .map(|res| res.unwrap_or_else(|err| err.resume()))
         }
     }
 }
@@ -331,6 +693,7 @@ mod unix {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::CancellationToken;
 
     #[test]
     fn auto_traits() {
@@ -340,4 +703,290 @@ mod tests {
         assert_sync(&runtime);
         assert_clone(&runtime);
     }
+
+    #[test]
+    fn spawn_local() {
+        let runtime = Runtime::tokio().unwrap();
+        let res = runtime.block_on(async {
+            let data = std::rc::Rc::new(21);
+            runtime.spawn_local(async move { *data * 2 }).await
+        });
+        assert_eq!(res, 42);
+        let outside = runtime.block_on(runtime.spawn({
+            let runtime = runtime.clone();
+            async move { runtime.try_spawn_local(async {}).map(drop) }
+        }));
+        assert_eq!(
+            outside,
+            Err(RuntimeError::Unsupported(
+                "spawning local tasks outside of block_on"
+            ))
+        );
+    }
+
+    #[test]
+    fn try_join_panic() {
+        async fn boom() -> u32 {
+            panic!("boom")
+        }
+
+        let runtime = Runtime::tokio().unwrap();
+        let res = runtime.block_on(runtime.spawn(boom()).try_join());
+        assert_eq!(res.unwrap_err().to_string(), "task panicked: boom");
+    }
+
+    #[test]
+    fn abort_handle() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            let task = runtime.spawn(std::future::pending::<()>());
+            let handle = task.abort_handle();
+            assert_eq!(handle.id(), task.id());
+            assert!(!handle.is_finished());
+            handle.abort();
+            assert!(task.try_join().await.unwrap_err().is_cancelled());
+            assert!(handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn cancel_finishes_abort_handles() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            let mut task = runtime.spawn(std::future::pending::<()>());
+            let handle = task.abort_handle();
+            assert_eq!(task.cancel().await, None);
+            assert!(task.is_finished());
+            assert!(handle.is_finished());
+            assert!(task.abort_handle().is_finished());
+        });
+    }
+
+    #[test]
+    fn abort_on_drop() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            let task = runtime.spawn(std::future::pending::<()>()).abort_on_drop();
+            let handle = task.abort_handle();
+            drop(task);
+            while !handle.is_finished() {
+                runtime.sleep(Duration::from_millis(1)).await;
+            }
+            let mut task = runtime.spawn(std::future::pending::<()>()).abort_on_drop();
+            assert_eq!(task.cancel().await, None);
+            assert!(task.is_finished());
+            let task = runtime.spawn(async { panic!("boom") }).abort_on_drop();
+            assert!(task.try_join().await.unwrap_err().is_panic());
+        });
+    }
+
+    #[test]
+    fn spawn_with_token() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            let token = CancellationToken::new();
+            let task = runtime.spawn_with_token(token.child_token(), std::future::pending::<()>());
+            token.cancel();
+            assert_eq!(task.await, None);
+        });
+    }
+
+    #[test]
+    fn spawn_blocking_cancellable() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            let mut task = runtime.spawn_blocking_cancellable(|token| {
+                let mut iterations = 0;
+                while !token.is_cancelled() {
+                    iterations += 1;
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                iterations
+            });
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            assert!(task.cancel().await.is_some_and(|iterations| iterations > 0));
+        });
+    }
+
+    #[test]
+    fn spawn_inheriting() {
+        crate::task_local! {
+            static REQUEST_ID: u32;
+        }
+        let runtime = Runtime::tokio().unwrap();
+        let id = runtime.block_on(REQUEST_ID.scope(7, async {
+            runtime
+                .spawn_inheriting(&[&REQUEST_ID], async {
+                    tokio::task::yield_now().await;
+                    REQUEST_ID.get()
+                })
+                .await
+        }));
+        assert_eq!(id, 7);
+    }
+
+    #[test]
+    fn current() {
+        assert!(TokioRuntime::try_current().is_none());
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            assert!(TokioRuntime::try_current().is_some());
+            let in_task = || async {
+                tokio::task::yield_now().await;
+                TokioRuntime::try_current().is_some()
+            };
+            assert!(runtime.spawn(in_task()).await);
+            runtime.set_task_context(false);
+            assert!(!runtime.spawn(in_task()).await);
+            assert!(
+                runtime
+                    .spawn_blocking(|| TokioRuntime::try_current().is_some())
+                    .await
+            );
+            assert!(crate::NoopRuntime::try_current().is_none());
+        });
+        assert!(TokioRuntime::try_current().is_none());
+    }
+
+    #[test]
+    fn capabilities() {
+        let runtime = Runtime::tokio().unwrap();
+        let capabilities = runtime.capabilities();
+        assert_eq!(runtime.kind(), "tokio");
+        assert!(capabilities.multi_threaded && capabilities.real_timers);
+        let runtime = Runtime::from_dyn(Arc::new(runtime));
+        assert_eq!(runtime.capabilities(), capabilities);
+    }
+
+    #[test]
+    fn liveness_tracking() {
+        let tokio = TokioRT::new().unwrap();
+        let metrics = tokio.metrics();
+        let runtime = Runtime::tokio_with_handle(tokio.handle().clone());
+        assert_eq!(metrics.num_alive_tasks(), 1);
+        drop(runtime);
+        while metrics.num_alive_tasks() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn try_spawn() {
+        let tokio = TokioRT::new().unwrap();
+        let handle = tokio.handle().clone();
+        let runtime = Runtime::tokio_with_handle(handle);
+        assert_eq!(runtime.try_block_on(async { 42 }), Ok(42));
+        assert!(runtime.try_spawn(async {}).is_ok());
+        runtime.block_on(async {
+            let nested = runtime
+                .spawn(async { Runtime::tokio_current().try_block_on(async {}) })
+                .await;
+            assert_eq!(nested, Err(RuntimeError::NestedBlockOn));
+        });
+        drop(tokio);
+        let err = runtime.try_spawn_blocking(|| ()).unwrap_err();
+        assert_eq!(err, RuntimeError::ShutDown);
+        assert!(runtime.is_runtime_shutdown_error(&err.into()));
+    }
+
+    #[test]
+    fn shutdown() {
+        let runtime = Runtime::tokio().unwrap();
+        let signal = runtime.shutdown_signal();
+        let drained = runtime.spawn(async move {
+            signal.await;
+        });
+        let stuck = runtime.spawn(std::future::pending::<()>()).try_join();
+        let (unblock, blocked) = std::sync::mpsc::channel::<()>();
+        let blocking = runtime.spawn_blocking(move || blocked.recv());
+        let report = runtime.shutdown(Duration::from_millis(100));
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 1,
+                aborted: 1,
+                detached: 1,
+            }
+        );
+        assert_eq!(
+            runtime.try_spawn(async {}).unwrap_err(),
+            RuntimeError::ShutDown
+        );
+        drop((drained, stuck, blocking, unblock));
+    }
+
+    #[test]
+    fn shutdown_async() {
+        let runtime = Runtime::tokio().unwrap();
+        let signal = runtime.shutdown_signal();
+        let drained = runtime.spawn(async move {
+            signal.await;
+        });
+        // Completes as soon as the tasks are drained rather than at the deadline
+        let report = crate::util::simple_block_on(runtime.shutdown_async(Duration::from_secs(60)));
+        assert_eq!(report.completed, 1);
+        drop(drained);
+    }
+
+    #[test]
+    fn drop_from_task() {
+        let runtime = Runtime::tokio().unwrap();
+        let weak = runtime.downgrade();
+        let token = CancellationToken::new();
+        let rt = runtime.clone();
+        let wait = token.clone();
+        let task = runtime.spawn(async move {
+            wait.cancelled().await;
+            // This is now the last clone
+            drop(rt);
+        });
+        drop(runtime);
+        token.cancel();
+        assert!(crate::util::simple_block_on(task.try_join()).is_ok());
+        while weak.upgrade().is_some() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn reentrant_block_on() {
+        use crate::util::reentrant_block_on;
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            assert_eq!(reentrant_block_on(async { 1 }), 1);
+            let task = runtime.spawn(async { reentrant_block_on(async { 2 }) });
+            assert_eq!(task.await, 2);
+        });
+        let runtime = Runtime::tokio_with_runtime(
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(
+            runtime.block_on(async { reentrant_block_on(async { 3 }) }),
+            3
+        );
+    }
+
+    #[test]
+    fn block_in_place() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            assert_eq!(runtime.block_in_place(|| 1), 1);
+            let rt = runtime.clone();
+            let task = runtime.spawn(async move { rt.block_in_place(|| 2) });
+            assert_eq!(task.await, 2);
+        });
+    }
+
+    #[test]
+    fn yield_now() {
+        let runtime = Runtime::tokio().unwrap();
+        runtime.block_on(async {
+            runtime.yield_now().await;
+            for _ in 0..1000 {
+                runtime.consume_budget().await;
+            }
+        });
+    }
 }
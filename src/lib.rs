@@ -9,6 +9,11 @@
 //! - smol: enable the smol implementation
 //! - async-global-executor: enable the async-global-executor implementation
 //! - async-io: enable the async-io reactor implementation
+//! - compute: enable `Runtime::spawn_compute`, running CPU-bound work on a rayon pool
+//! - sharded: enable the thread-per-core implementation, running one pinned runtime per core
+//!
+//! The builtin implementation only needs `std` and is always available, it is used as a fallback
+//! when no other implementation is enabled.
 //!
 //! ## Example
 //!
@@ -41,6 +46,12 @@
 //! # }
 //! ```
 
+mod error;
+pub use error::*;
+
+mod global;
+pub use global::*;
+
 mod runtime;
 pub use runtime::*;
 
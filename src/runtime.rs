@@ -1,27 +1,183 @@
 use crate::{
+    RuntimeError,
     sys::AsSysFd,
-    traits::{Executor, Reactor, RuntimeKit},
-    util::{SocketAddrsResolver, Task},
+    traits::{Capabilities, DynRuntime, Executor, LocalExecutor, Reactor, RuntimeKit},
+    util::{
+        self, BlockingPool, BlockingPoolStats, DynIO, DynOutput, DynSocket, DynTaskImpl, PoolTask,
+        Scope, ScopeFuture, SocketAddrsResolver, Task,
+    },
 };
 use futures_core::Stream;
 use futures_io::{AsyncRead, AsyncWrite};
 use std::{
-    future::Future,
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    future::{self, Future},
     io::{self, Read, Write},
     net::{SocketAddr, ToSocketAddrs},
+    ops::Deref,
+    pin::{Pin, pin},
+    sync::{
+        Arc, Mutex, MutexGuard, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
     time::{Duration, Instant},
 };
 
 /// A full-featured Runtime implementation
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct Runtime<RK: RuntimeKit> {
+    kit: Arc<Shared<RK>>,
+}
+
+// The RuntimeKit along with the state owned by the Runtime, shared by all of its clones
+#[derive(Debug)]
+struct Shared<RK> {
     kit: RK,
+    pools: Mutex<HashMap<String, BlockingPool>>,
+    task_context: AtomicBool,
+}
+
+impl<RK> Deref for Shared<RK> {
+    type Target = RK;
+
+    fn deref(&self) -> &RK {
+        &self.kit
+    }
 }
 
 impl<RK: RuntimeKit> Runtime<RK> {
     /// Create a new Runtime from a RuntimeKit
     pub fn new(kit: RK) -> Self {
-        Self { kit }
+        Self {
+            kit: Arc::new(Shared {
+                kit,
+                pools: Mutex::default(),
+                task_context: AtomicBool::new(true),
+            }),
+        }
+    }
+
+    #[cfg(any(feature = "sharded", feature = "tokio"))]
+    pub(crate) fn kit(&self) -> &RK {
+        &self.kit.kit
+    }
+
+    // Makes the runtime current while polling the future built by `make`, wherever it gets built,
+    // unless disabled with `set_task_context`
+    #[cfg(feature = "sharded")]
+    pub(crate) fn in_context<Fut: Future + 'static, F: FnOnce() -> Fut>(
+        &self,
+        make: F,
+    ) -> impl FnOnce() -> Pin<Box<dyn Future<Output = Fut::Output>>> + use<RK, Fut, F> {
+        let kit = self.task_context().then(|| Arc::downgrade(&self.kit));
+        move || Box::pin(with_runtime(kit, make()))
+    }
+
+    /// Whether to make this runtime current while polling the tasks it spawns, so that
+    /// `Runtime::current` works from within them.
+    ///
+    /// This is enabled by default. Disabling it saves a thread-local swap each time a task gets
+    /// polled, tasks spawned afterwards then no longer see this runtime as current. The runtime is
+    /// always current inside `block_on` and blocking closures.
+    pub fn set_task_context(&self, enabled: bool) {
+        self.kit.task_context.store(enabled, Ordering::Relaxed);
+    }
+
+    fn task_context(&self) -> bool {
+        self.kit.task_context.load(Ordering::Relaxed)
+    }
+
+    fn pools(&self) -> MutexGuard<'_, HashMap<String, BlockingPool>> {
+        self.kit.pools.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register a named blocking pool on this runtime, replacing any previous one with the same
+    /// name, see `spawn_blocking_on`.
+    ///
+    /// `to_socket_addrs` uses the pool named "dns" when there is one.
+    pub fn add_blocking_pool(&self, pool: BlockingPool) {
+        self.pools().insert(pool.name().to_owned(), pool);
+    }
+
+    /// Get the blocking pool registered with this name, if any
+    pub fn blocking_pool(&self, name: &str) -> Option<BlockingPool> {
+        self.pools().get(name).cloned()
+    }
+
+    /// Get a snapshot of the activity of all the registered blocking pools, by name
+    pub fn blocking_pool_stats(&self) -> HashMap<String, BlockingPoolStats> {
+        self.pools()
+            .iter()
+            .map(|(name, pool)| (name.clone(), pool.stats()))
+            .collect()
+    }
+
+    /// Run a CPU-bound closure on the compute pool.
+    ///
+    /// Unlike `spawn_blocking`, which is meant for blocking I/O, the compute pool is a fixed-size
+    /// work-stealing pool with one thread per available core, shared by all runtimes.
+    #[cfg(feature = "compute")]
+    pub fn spawn_compute<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<PoolTask<T>> {
+        util::spawn_compute(with_runtime_blocking(&self.kit, f))
+    }
+
+    /// Run a blocking closure on the blocking pool registered with this name.
+    ///
+    /// Fails with `RuntimeError::UnknownPool` if there is no such pool, `RuntimeError::LimitReached`
+    /// if too many closures are already waiting in line, or `RuntimeError::Io` if no thread can be
+    /// started.
+    pub fn spawn_blocking_on<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        pool: &str,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        self.blocking_pool(pool)
+            .ok_or(RuntimeError::UnknownPool)?
+            .spawn(with_runtime_blocking(&self.kit, f))
+    }
+
+    /// Create a weak handle to this runtime, which doesn't keep it alive
+    pub fn downgrade(&self) -> WeakRuntime<RK> {
+        WeakRuntime {
+            kit: Arc::downgrade(&self.kit),
+        }
+    }
+
+    /// Get the runtime driving the current task.
+    ///
+    /// This is set while inside `block_on`, while running blocking closures spawned through a
+    /// `Runtime` and, unless disabled with `set_task_context`, while running its tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current task isn't driven by a runtime of this type.
+    pub fn current() -> Self {
+        Self::try_current().expect("not running inside of an async_rs Runtime of this type")
+    }
+
+    /// Get the runtime driving the current task, if any, and if it is of this type.
+    pub fn try_current() -> Option<Self> {
+        let current = CURRENT.with(|current| current.borrow().clone())?;
+        Arc::downcast(current.kit).ok().map(|kit| Self { kit })
+    }
+
+    // Nested block_on calls are forbidden when entering an asynchronous context
+    fn enter(&self, asynchronous: bool) -> EnterGuard {
+        let current = Current {
+            kit: self.kit.clone(),
+            asynchronous,
+            to_dyn: |kit| {
+                Arc::new(Runtime::<RK> {
+                    kit: Arc::downcast(kit).expect("current runtime has an unexpected type"),
+                })
+            },
+        };
+        EnterGuard(CURRENT.with(|c| c.borrow_mut().replace(current)))
     }
 
     /// Asynchronously resolve the given domain name
@@ -38,18 +194,89 @@ impl<RK: RuntimeKit> Runtime<RK> {
         }
     }
 
+    /// Run a scope in which futures borrowing from the enclosing stack frame can be spawned.
+    ///
+    /// The children are driven concurrently by the returned future itself, on the task awaiting
+    /// it, rather than being spawned on the executor: a leaked scope future then cannot leave
+    /// children running with dangling borrows. The scope completes once its body and all of its
+    /// children completed, dropping it cancels all of them.
+    ///
+    /// ```rust
+    /// # use async_rs::{Runtime, traits::*};
+    /// let rt = Runtime::noop();
+    /// let data = vec![1, 2, 3];
+    /// let data = &data;
+    /// let sum = rt.block_on(rt.scope(|s| async move {
+    ///     let first = s.spawn(async move { data[0] });
+    ///     let rest = s.spawn(async move { data[1..].iter().sum::<i32>() });
+    ///     first.await + rest.await
+    /// }));
+    /// assert_eq!(sum, 6);
+    /// ```
+    pub fn scope<'env, F: FnOnce(Scope<'env>) -> Fut, Fut: Future + 'env>(
+        &self,
+        f: F,
+    ) -> ScopeFuture<'env, Fut> {
+        util::scope(f)
+    }
+
+    /// Report what the underlying RuntimeKit supports
+    pub fn capabilities(&self) -> Capabilities {
+        self.kit.capabilities()
+    }
+
+    /// The name of the executor backing this runtime, e.g. "tokio"
+    pub fn kind(&self) -> &'static str {
+        self.kit.kind()
+    }
+
     /// Check if an `std::io::Error` is a runtime shutdown error
+    ///
+    /// This recognizes `RuntimeError::ShutDown` and tokio's own shutdown errors. smol and
+    /// async-global-executor run on global executors which never shut down, so they have none.
     pub fn is_runtime_shutdown_error(&self, err: &io::Error) -> bool {
+        if RuntimeError::from_io_error(err) == Some(RuntimeError::ShutDown) {
+            return true;
+        }
         #[cfg(feature = "tokio")]
         if tokio::runtime::is_rt_shutdown_err(err) {
             return true;
         }
-        #[cfg(not(feature = "tokio"))]
-        let _ = err;
         false
     }
 }
 
+impl<RK: RuntimeKit> Clone for Runtime<RK> {
+    fn clone(&self) -> Self {
+        Self {
+            kit: self.kit.clone(),
+        }
+    }
+}
+
+/// A weak handle to a Runtime, returned by `Runtime::downgrade`.
+///
+/// This lets background tasks reach the runtime without keeping it alive.
+#[derive(Debug)]
+pub struct WeakRuntime<RK: RuntimeKit> {
+    kit: Weak<Shared<RK>>,
+}
+
+impl<RK: RuntimeKit> WeakRuntime<RK> {
+    /// Get the runtime back, if it is still alive
+    pub fn upgrade(&self) -> Option<Runtime<RK>> {
+        self.kit.upgrade().map(|kit| Runtime { kit })
+    }
+}
+
+impl<RK: RuntimeKit> Clone for WeakRuntime<RK> {
+    fn clone(&self) -> Self {
+        Self {
+            kit: self.kit.clone(),
+        }
+    }
+}
+
 impl<RK: RuntimeKit> From<RK> for Runtime<RK> {
     fn from(kit: RK) -> Self {
         Self::new(kit)
@@ -60,6 +287,7 @@ impl<RK: RuntimeKit> Executor for Runtime<RK> {
     type Task<T: Send + 'static> = <RK as Executor>::Task<T>;
 
     fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
+        let _guard = self.enter(true);
         self.kit.block_on(f)
     }
 
@@ -67,14 +295,89 @@ impl<RK: RuntimeKit> Executor for Runtime<RK> {
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        self.kit.spawn(f)
+        if self.task_context() {
+            self.kit
+                .spawn(with_runtime(Some(Arc::downgrade(&self.kit)), f))
+        } else {
+            self.kit.spawn(f)
+        }
     }
 
     fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
         &self,
         f: F,
     ) -> Task<Self::Task<T>> {
-        self.kit.spawn_blocking(f)
+        self.kit.spawn_blocking(with_runtime_blocking(&self.kit, f))
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        if in_async_context() {
+            return Err(RuntimeError::NestedBlockOn);
+        }
+        let _guard = self.enter(true);
+        self.kit.try_block_on(f)
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        if self.task_context() {
+            self.kit
+                .try_spawn(with_runtime(Some(Arc::downgrade(&self.kit)), f))
+        } else {
+            self.kit.try_spawn(f)
+        }
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.kit
+            .try_spawn_blocking(with_runtime_blocking(&self.kit, f))
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.kit.block_in_place(f)
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.kit.yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.kit.consume_budget()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        self.kit.executor_capabilities()
+    }
+}
+
+impl<RK: RuntimeKit + LocalExecutor> LocalExecutor for Runtime<RK> {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        if self.task_context() {
+            self.kit
+                .spawn_local(with_runtime(Some(Arc::downgrade(&self.kit)), f))
+        } else {
+            self.kit.spawn_local(f)
+        }
+    }
+
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        if self.task_context() {
+            self.kit
+                .try_spawn_local(with_runtime(Some(Arc::downgrade(&self.kit)), f))
+        } else {
+            self.kit.try_spawn_local(f)
+        }
     }
 }
 
@@ -103,4 +406,148 @@ impl<RK: RuntimeKit> Reactor for Runtime<RK> {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         self.kit.tcp_connect_addr(addr)
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        self.kit.reactor_capabilities()
+    }
+}
+
+impl<RK: RuntimeKit> DynRuntime for Runtime<RK> {
+    fn block_on_dyn(&self, f: Pin<Box<dyn Future<Output = ()> + '_>>) {
+        self.block_on(f);
+    }
+
+    fn spawn_dyn(
+        &self,
+        f: Pin<Box<dyn Future<Output = DynOutput> + Send>>,
+    ) -> Box<dyn DynTaskImpl> {
+        Box::new(self.spawn(f))
+    }
+
+    fn spawn_blocking_dyn(&self, f: Box<dyn FnOnce() -> DynOutput + Send>) -> Box<dyn DynTaskImpl> {
+        Box::new(self.spawn_blocking(f))
+    }
+
+    fn try_block_on_dyn(
+        &self,
+        f: Pin<Box<dyn Future<Output = ()> + '_>>,
+    ) -> Result<(), RuntimeError> {
+        self.try_block_on(f)
+    }
+
+    fn try_spawn_dyn(
+        &self,
+        f: Pin<Box<dyn Future<Output = DynOutput> + Send>>,
+    ) -> Result<Box<dyn DynTaskImpl>, RuntimeError> {
+        Ok(Box::new(self.try_spawn(f)?))
+    }
+
+    fn try_spawn_blocking_dyn(
+        &self,
+        f: Box<dyn FnOnce() -> DynOutput + Send>,
+    ) -> Result<Box<dyn DynTaskImpl>, RuntimeError> {
+        Ok(Box::new(self.try_spawn_blocking(f)?))
+    }
+
+    fn block_in_place_dyn(&self, f: &mut dyn FnMut()) {
+        self.block_in_place(f);
+    }
+
+    fn yield_now_dyn(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(self.yield_now())
+    }
+
+    fn consume_budget_dyn(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(self.consume_budget())
+    }
+
+    fn register_dyn(&self, socket: DynSocket) -> io::Result<DynIO> {
+        Ok(Box::new(self.register(socket)?))
+    }
+
+    fn sleep_dyn(&self, dur: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let sleep = self.sleep(dur);
+        Box::pin(async move {
+            sleep.await;
+        })
+    }
+
+    fn interval_dyn(&self, dur: Duration) -> Pin<Box<dyn Stream<Item = Instant> + Send>> {
+        Box::pin(self.interval(dur))
+    }
+
+    fn tcp_connect_addr_dyn(
+        &self,
+        addr: SocketAddr,
+    ) -> Pin<Box<dyn Future<Output = io::Result<DynIO>> + Send>> {
+        let connect = self.tcp_connect_addr(addr);
+        Box::pin(async move { Ok(Box::new(connect.await?) as DynIO) })
+    }
+
+    fn capabilities_dyn(&self) -> Capabilities {
+        self.capabilities()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone)]
+struct Current {
+    kit: Arc<dyn Any + Send + Sync>,
+    asynchronous: bool,
+    to_dyn: fn(Arc<dyn Any + Send + Sync>) -> Arc<dyn DynRuntime>,
+}
+
+/// Get the runtime driving the current task, whatever its type
+pub(crate) fn current_dyn() -> Option<Arc<dyn DynRuntime>> {
+    let current = CURRENT.with(|current| current.borrow().clone())?;
+    Some((current.to_dyn)(current.kit))
+}
+
+/// Check whether we're inside `block_on` or a task spawned through a `Runtime`
+pub(crate) fn in_async_context() -> bool {
+    CURRENT.with(|current| current.borrow().as_ref().is_some_and(|c| c.asynchronous))
+}
+
+// Restores the previous current runtime when leaving a runtime context
+struct EnterGuard(Option<Current>);
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+// Makes the runtime current while running a blocking closure
+fn with_runtime_blocking<RK: RuntimeKit, T, F: FnOnce() -> T>(
+    kit: &Arc<Shared<RK>>,
+    f: F,
+) -> impl FnOnce() -> T + use<RK, T, F> {
+    let kit = Arc::downgrade(kit);
+    move || {
+        let _guard = kit.upgrade().map(|kit| Runtime { kit }.enter(false));
+        f()
+    }
+}
+
+// Makes the runtime current while polling a spawned future, if any.
+// Only a weak reference is kept so that tasks don't keep their own runtime alive.
+fn with_runtime<RK: RuntimeKit, F: Future>(
+    kit: Option<Weak<Shared<RK>>>,
+    f: F,
+) -> impl Future<Output = F::Output> + use<RK, F> {
+    async move {
+        let mut f = pin!(f);
+        future::poll_fn(|cx| {
+            let _guard = kit
+                .as_ref()
+                .and_then(Weak::upgrade)
+                .map(|kit| Runtime { kit }.enter(true));
+            f.as_mut().poll(cx)
+        })
+        .await
+    }
 }
@@ -4,6 +4,23 @@ use std::os::unix::io::{AsFd, AsRawFd};
 pub trait AsSysFd: AsFd + AsRawFd {}
 impl<H: AsFd + AsRawFd> AsSysFd for H {}
 
+mod dynamic {
+    use crate::util::DynSocket;
+    use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+    impl AsFd for DynSocket {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            self.0.as_fd()
+        }
+    }
+
+    impl AsRawFd for DynSocket {
+        fn as_raw_fd(&self) -> RawFd {
+            self.0.as_raw_fd()
+        }
+    }
+}
+
 #[cfg(feature = "async-io")]
 mod async_io {
     use crate::{sys::AsSysFd, util::IOHandle};
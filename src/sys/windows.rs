@@ -4,6 +4,23 @@ use std::os::windows::io::{AsRawSocket, AsSocket};
 pub trait AsSysFd: AsSocket + AsRawSocket {}
 impl<H: AsSocket + AsRawSocket> AsSysFd for H {}
 
+mod dynamic {
+    use crate::util::DynSocket;
+    use std::os::windows::io::{AsRawSocket, AsSocket, BorrowedSocket, RawSocket};
+
+    impl AsSocket for DynSocket {
+        fn as_socket(&self) -> BorrowedSocket<'_> {
+            self.0.as_socket()
+        }
+    }
+
+    impl AsRawSocket for DynSocket {
+        fn as_raw_socket(&self) -> RawSocket {
+            self.0.as_raw_socket()
+        }
+    }
+}
+
 #[cfg(feature = "async-io")]
 mod async_io {
     use crate::{sys::AsSysFd, util::IOHandle};
@@ -1,6 +1,12 @@
 //! A collection of traits to define a common interface across executors
 
-use crate::util::{Task, TaskImpl};
+use crate::{
+    RuntimeError,
+    traits::Capabilities,
+    util::{
+        self, CancellableTask, CancellationToken, InheritTaskLocal, Task, TaskImpl, TaskLocals,
+    },
+};
 use std::{future::Future, ops::Deref};
 
 /// A common interface for spawning futures on top of an executor
@@ -28,6 +34,128 @@ pub trait Executor {
     ) -> Task<Self::Task<T>>
     where
         Self: Sized;
+
+    /// Block on a future until completion, failing instead of panicking if it cannot be done
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError>
+    where
+        Self: Sized,
+    {
+        Ok(self.block_on(f))
+    }
+
+    /// Spawn a future and return a handle to track its completion, failing instead of returning
+    /// a broken task if it cannot be done
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError>
+    where
+        Self: Sized,
+    {
+        Ok(self.spawn(f))
+    }
+
+    /// Convert a blocking task into a future, failing instead of returning a broken task if it
+    /// cannot be done
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError>
+    where
+        Self: Sized,
+    {
+        Ok(self.spawn_blocking(f))
+    }
+
+    /// Run a short blocking section in place, letting the executor know it may block.
+    ///
+    /// This only moves the other tasks away from the current thread on executors supporting it,
+    /// such as tokio's multi-threaded runtime. The default implementation runs the closure inline,
+    /// blocking the current thread and any task sharing it, use `spawn_blocking` for long
+    /// blocking sections instead.
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T
+    where
+        Self: Sized,
+    {
+        f()
+    }
+
+    /// Yield back to the executor once, letting the other tasks run
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static
+    where
+        Self: Sized,
+    {
+        util::yield_now()
+    }
+
+    /// Consume a unit of the cooperative scheduling budget of the current task, yielding back to
+    /// the executor once it is exhausted.
+    ///
+    /// Call this from long loops so that they don't starve the other tasks. The default
+    /// implementation yields every `util::BUDGET` calls.
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static
+    where
+        Self: Sized,
+    {
+        util::consume_budget()
+    }
+
+    /// Spawn a future which gets canceled when the given token is.
+    ///
+    /// The returned task resolves to None if the future got canceled before completing.
+    fn spawn_with_token<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        token: CancellationToken,
+        f: F,
+    ) -> Task<Self::Task<Option<T>>>
+    where
+        Self: Sized,
+    {
+        self.spawn(async move { token.run_until_cancelled(f).await })
+    }
+
+    /// Convert a blocking task into a future, spawning it on a dedicated thread pool, giving it a
+    /// token it can check to stop early.
+    ///
+    /// Canceling the returned task cancels the token and waits for the closure to return.
+    fn spawn_blocking_cancellable<
+        T: Send + 'static,
+        F: FnOnce(CancellationToken) -> T + Send + 'static,
+    >(
+        &self,
+        f: F,
+    ) -> Task<CancellableTask<Self::Task<T>>>
+    where
+        Self: Sized,
+    {
+        let token = CancellationToken::new();
+        let task = self.spawn_blocking({
+            let token = token.clone();
+            move || f(token)
+        });
+        CancellableTask::new(task, token).into()
+    }
+
+    /// Spawn a future which sees a copy of the current values of the given task-local keys.
+    ///
+    /// Keys which aren't set in the current scope are left unset in the spawned task.
+    fn spawn_inheriting<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        keys: &[&'static dyn InheritTaskLocal],
+        f: F,
+    ) -> Task<Self::Task<T>>
+    where
+        Self: Sized,
+    {
+        self.spawn(TaskLocals::capture(keys).scope(f))
+    }
+
+    /// Report what this executor supports.
+    ///
+    /// Only the executor related fields are meaningful, see `Capabilities::merge`.
+    fn executor_capabilities(&self) -> Capabilities {
+        Capabilities::NONE
+    }
 }
 
 impl<E: Deref> Executor for E
@@ -53,4 +181,89 @@ where
     ) -> Task<Self::Task<T>> {
         self.deref().spawn_blocking(f)
     }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        self.deref().try_block_on(f)
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.deref().try_spawn(f)
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.deref().try_spawn_blocking(f)
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.deref().block_in_place(f)
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.deref().yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.deref().consume_budget()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        self.deref().executor_capabilities()
+    }
+}
+
+/// A common interface for spawning non-Send futures on the current thread
+pub trait LocalExecutor: Executor {
+    /// Spawn a non-Send future on the current thread and return a handle to track its completion.
+    ///
+    /// The future is driven by the executor while the current thread is inside `block_on`, so this
+    /// should be called from within `block_on`.
+    ///
+    /// # Panics
+    ///
+    /// Some executors cannot spawn local tasks from everywhere: tokio only supports it from within
+    /// `block_on`, and panics when called from a task started with `spawn`. Use `try_spawn_local`
+    /// to get an error instead.
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>>
+    where
+        Self: Sized;
+
+    /// Spawn a non-Send future on the current thread, failing instead of panicking if it cannot be
+    /// done from here
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError>
+    where
+        Self: Sized,
+    {
+        Ok(self.spawn_local(f))
+    }
+}
+
+impl<E: Deref> LocalExecutor for E
+where
+    E::Target: LocalExecutor + Sized,
+{
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.deref().spawn_local(f)
+    }
+
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.deref().try_spawn_local(f)
+    }
 }
@@ -1,6 +1,9 @@
 //! A collection of traits to define a common interface across reactors
 
-use crate::{sys::AsSysFd, traits::AsyncToSocketAddrs};
+use crate::{
+    sys::AsSysFd,
+    traits::{AsyncToSocketAddrs, Capabilities},
+};
 use futures_core::Stream;
 use futures_io::{AsyncRead, AsyncWrite};
 use std::{
@@ -65,6 +68,13 @@ pub trait Reactor {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static
     where
         Self: Sized;
+
+    /// Report what this reactor supports.
+    ///
+    /// Only the reactor related fields are meaningful, see `Capabilities::merge`.
+    fn reactor_capabilities(&self) -> Capabilities {
+        Capabilities::NONE
+    }
 }
 
 impl<R: Deref> Reactor for R
@@ -95,4 +105,8 @@ where
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         self.deref().tcp_connect_addr(addr)
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        self.deref().reactor_capabilities()
+    }
 }
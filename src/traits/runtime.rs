@@ -1,4 +1,148 @@
-use crate::traits::{Executor, Reactor};
+use crate::{
+    RuntimeError,
+    traits::{Executor, Reactor},
+    util::{DynIO, DynOutput, DynSocket, DynTaskImpl},
+};
+use futures_core::Stream;
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
 /// Supertrait to tag a type that implements all required components for a Runtime
-pub trait RuntimeKit: Executor + Reactor + std::fmt::Debug {}
+///
+/// Since 0.9, kits must be `Send + Sync + 'static` so that `Runtime::current` can hand them out
+/// to any thread. This is a breaking change for kits which weren't.
+pub trait RuntimeKit: Executor + Reactor + std::fmt::Debug + Send + Sync + 'static {
+    /// Report what this kit supports, combining its executor and reactor capabilities
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::merge(self.executor_capabilities(), self.reactor_capabilities())
+    }
+
+    /// The name of the executor backing this kit, e.g. "tokio"
+    fn kind(&self) -> &'static str {
+        self.capabilities().executor
+    }
+}
+
+/// What a runtime supports, as reported by `RuntimeKit::capabilities`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The name of the executor
+    pub executor: &'static str,
+    /// The name of the reactor
+    pub reactor: &'static str,
+    /// Whether synchronous handles can be registered on the reactor
+    pub fd_registration: bool,
+    /// Whether non-Send futures can be spawned on the current thread
+    pub local_spawn: bool,
+    /// Whether timers actually wait for the given duration
+    pub real_timers: bool,
+    /// Whether blocking closures run on a dedicated thread pool
+    pub blocking_pool: bool,
+    /// Whether domain names are resolved asynchronously instead of on the blocking pool
+    pub async_dns: bool,
+    /// Whether spawned futures can run on several threads in parallel
+    pub multi_threaded: bool,
+}
+
+impl Capabilities {
+    /// Capabilities of something which doesn't support anything
+    pub const NONE: Self = Self {
+        executor: "unknown",
+        reactor: "unknown",
+        fd_registration: false,
+        local_spawn: false,
+        real_timers: false,
+        blocking_pool: false,
+        async_dns: false,
+        multi_threaded: false,
+    };
+
+    /// Combine the executor related capabilities of `executor` with the reactor related ones of
+    /// `reactor`
+    pub fn merge(executor: Self, reactor: Self) -> Self {
+        Self {
+            executor: executor.executor,
+            reactor: reactor.reactor,
+            fd_registration: reactor.fd_registration,
+            local_spawn: executor.local_spawn,
+            real_timers: reactor.real_timers,
+            blocking_pool: executor.blocking_pool,
+            async_dns: reactor.async_dns,
+            multi_threaded: executor.multi_threaded,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// An object-safe interface to a Runtime, allowing to pick its implementation at runtime.
+///
+/// It is implemented by every `Runtime` and can be turned back into a full-featured Runtime
+/// with `DynamicRuntime::from_dyn`.
+pub trait DynRuntime: fmt::Debug + Send + Sync + 'static {
+    /// Block on a future until completion
+    fn block_on_dyn(&self, f: Pin<Box<dyn Future<Output = ()> + '_>>);
+
+    /// Spawn a future and return a handle to track its completion
+    fn spawn_dyn(&self, f: Pin<Box<dyn Future<Output = DynOutput> + Send>>)
+    -> Box<dyn DynTaskImpl>;
+
+    /// Convert a blocking task into a future, spawning it on a dedicated thread pool
+    fn spawn_blocking_dyn(&self, f: Box<dyn FnOnce() -> DynOutput + Send>) -> Box<dyn DynTaskImpl>;
+
+    /// Block on a future until completion, failing if it cannot be done
+    fn try_block_on_dyn(
+        &self,
+        f: Pin<Box<dyn Future<Output = ()> + '_>>,
+    ) -> Result<(), RuntimeError>;
+
+    /// Spawn a future and return a handle to track its completion, failing if it cannot be done
+    fn try_spawn_dyn(
+        &self,
+        f: Pin<Box<dyn Future<Output = DynOutput> + Send>>,
+    ) -> Result<Box<dyn DynTaskImpl>, RuntimeError>;
+
+    /// Convert a blocking task into a future, failing if it cannot be done
+    fn try_spawn_blocking_dyn(
+        &self,
+        f: Box<dyn FnOnce() -> DynOutput + Send>,
+    ) -> Result<Box<dyn DynTaskImpl>, RuntimeError>;
+
+    /// Run a short blocking section in place, see `Executor::block_in_place`
+    fn block_in_place_dyn(&self, f: &mut dyn FnMut());
+
+    /// Yield back to the executor once, see `Executor::yield_now`
+    fn yield_now_dyn(&self) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    /// Consume a unit of the scheduling budget, see `Executor::consume_budget`
+    fn consume_budget_dyn(&self) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    /// Register a synchronous handle, returning an asynchronous one
+    fn register_dyn(&self, socket: DynSocket) -> io::Result<DynIO>;
+
+    /// Sleep for the given duration
+    fn sleep_dyn(&self, dur: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    /// Stream that yields at every given interval
+    fn interval_dyn(&self, dur: Duration) -> Pin<Box<dyn Stream<Item = Instant> + Send>>;
+
+    /// Create a TcpStream by connecting to a specific pre-resolved address
+    fn tcp_connect_addr_dyn(
+        &self,
+        addr: SocketAddr,
+    ) -> Pin<Box<dyn Future<Output = io::Result<DynIO>> + Send>>;
+
+    /// Report what this runtime supports
+    fn capabilities_dyn(&self) -> Capabilities;
+}
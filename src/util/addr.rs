@@ -5,6 +5,7 @@ use crate::{
 use std::{
     fmt, future, io,
     net::{IpAddr, SocketAddr, ToSocketAddrs},
+    pin::Pin,
 };
 
 /// Wrapper to impl AsyncToSocketAddrs from an IntoIterator<Item = SocketAddr>
@@ -66,7 +67,17 @@ where
     + Send
     + 'static {
         let SocketAddrsResolver { runtime, addrs } = self;
-        runtime.spawn_blocking(move || addrs.to_socket_addrs())
+        let resolve = move || addrs.to_socket_addrs();
+        // Keep slow lookups away from the shared blocking pool when there is a dedicated one
+        let task: Pin<Box<dyn Future<Output = io::Result<A::Iter>> + Send>> =
+            match runtime.blocking_pool("dns") {
+                Some(pool) => match pool.spawn(resolve) {
+                    Ok(task) => Box::pin(task),
+                    Err(err) => Box::pin(future::ready(Err(err.into()))),
+                },
+                None => Box::pin(runtime.spawn_blocking(resolve)),
+            };
+        task
     }
 }
 
@@ -23,6 +23,49 @@ pub fn simple_block_on<F: Future>(f: F) -> F::Output {
     }
 }
 
+/// Block on the given future, even from within an asynchronous context.
+///
+/// This is meant for sync-over-async shims which may get called from anywhere. Outside of any
+/// asynchronous context, this is `simple_block_on`. Nested calls are detected and handled as
+/// follows:
+///
+/// - On a tokio multi-threaded runtime, the current worker is turned into a blocking thread with
+///   `tokio::task::block_in_place`, its other tasks get moved to other workers in the meantime.
+/// - Anywhere else, the future is handed off to a new helper thread, running it on the shared
+///   bridge runtime if we were inside of a tokio context (see `set_bridge_runtime`), and the
+///   current thread blocks until it completes.
+///
+/// Either way, the current thread is blocked and cannot make progress on its other tasks in the
+/// meantime, and the helper thread costs a thread spawn per call. A future relying on resources
+/// driven by the current thread, such as the IO driver of a current-thread tokio runtime, will
+/// never complete.
+pub fn reentrant_block_on<F: Future + Send>(f: F) -> F::Output
+where
+    F::Output: Send,
+{
+    #[cfg(feature = "tokio")]
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        if crate::util::can_block_in_place(&handle) {
+            return tokio::task::block_in_place(move || handle.block_on(f));
+        }
+        let bridge = crate::util::bridge_runtime()
+            .expect("failed to build a tokio runtime for reentrant_block_on");
+        return on_helper_thread(move || bridge.block_on(f));
+    }
+    if BUSY.with(|busy| busy.load(Ordering::Acquire)) || crate::runtime::in_async_context() {
+        return on_helper_thread(move || simple_block_on(f));
+    }
+    simple_block_on(f)
+}
+
+fn on_helper_thread<T: Send, F: FnOnce() -> T + Send>(f: F) -> T {
+    thread::scope(|s| {
+        s.spawn(f)
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
+
 thread_local! {
     static BUSY: AtomicBool = const { AtomicBool::new(false) };
 }
@@ -109,4 +152,10 @@ mod test {
         });
         assert_eq!(simple_block_on(fut), 10);
     }
+
+    #[test]
+    fn reentrant() {
+        let res = simple_block_on(async { reentrant_block_on(future::ready(42)) });
+        assert_eq!(res, 42);
+    }
 }
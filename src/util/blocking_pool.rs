@@ -0,0 +1,199 @@
+use crate::{
+    RuntimeError,
+    util::{PoolTask, Task},
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+
+// How long an idle thread waits for a new job before exiting
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A named pool of threads running blocking closures, with its own bounds.
+///
+/// Threads are started on demand up to `max_threads`, and exit after being idle for a while.
+/// Once all of them are busy, up to `max_queued` closures wait in line, spawning more fails with
+/// `RuntimeError::LimitReached`. Failing to start a thread is reported as `RuntimeError::Io`.
+/// Clones share the same pool.
+///
+/// Pools are usually registered on a Runtime with `Runtime::add_blocking_pool` and used through
+/// `Runtime::spawn_blocking_on`.
+#[derive(Clone)]
+pub struct BlockingPool(Arc<Pool>);
+
+struct Pool {
+    name: String,
+    max_threads: usize,
+    max_queued: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    queue: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+/// A snapshot of the activity of a `BlockingPool`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockingPoolStats {
+    /// The number of threads currently started
+    pub threads: usize,
+    /// The number of threads waiting for work
+    pub idle: usize,
+    /// The number of closures waiting for a thread
+    pub queued: usize,
+}
+
+impl BlockingPool {
+    /// Create a new pool running at most `max_threads` closures at once, with up to `max_queued`
+    /// more waiting in line
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_threads` is zero, as nothing would ever run.
+    pub fn new(name: impl Into<String>, max_threads: usize, max_queued: usize) -> Self {
+        assert!(max_threads > 0, "Blocking pools need at least one thread");
+        Self(Arc::new(Pool {
+            name: name.into(),
+            max_threads,
+            max_queued,
+            state: Mutex::default(),
+            available: Condvar::new(),
+        }))
+    }
+
+    /// The name of this pool
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Get a snapshot of the activity of this pool
+    pub fn stats(&self) -> BlockingPoolStats {
+        let state = self.0.lock();
+        BlockingPoolStats {
+            threads: state.threads,
+            idle: state.idle,
+            queued: state.queue.len(),
+        }
+    }
+
+    /// Run a blocking closure on this pool, failing if too many are already waiting in line
+    pub fn spawn<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<PoolTask<T>>, RuntimeError> {
+        let pool = &self.0;
+        let mut state = pool.lock();
+        let spare = state.idle + pool.max_threads.saturating_sub(state.threads);
+        if state.queue.len() >= spare.saturating_add(pool.max_queued) {
+            return Err(RuntimeError::LimitReached);
+        }
+        if state.queue.len() >= state.idle && state.threads < pool.max_threads {
+            // The new thread waits for us to release the lock before looking for work
+            let runner = pool.clone();
+            thread::Builder::new()
+                .name(pool.name.clone())
+                .spawn(move || runner.run())?;
+            state.threads += 1;
+        } else {
+            pool.available.notify_one();
+        }
+        let (task, delivery) = PoolTask::pending();
+        state.queue.push_back(Box::new(delivery.run_blocking(f)));
+        Ok(task.into())
+    }
+}
+
+impl fmt::Debug for BlockingPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingPool")
+            .field("name", &self.0.name)
+            .field("max_threads", &self.0.max_threads)
+            .field("max_queued", &self.0.max_queued)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Pool {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run(&self) {
+        let mut state = self.lock();
+        loop {
+            if let Some(job) = state.queue.pop_front() {
+                drop(state);
+                job();
+                state = self.lock();
+                continue;
+            }
+            state.idle += 1;
+            let (guard, timeout) = self
+                .available
+                .wait_timeout(state, KEEP_ALIVE)
+                .unwrap_or_else(|e| e.into_inner());
+            state = guard;
+            state.idle -= 1;
+            if timeout.timed_out() && state.queue.is_empty() {
+                state.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{CancellationToken, simple_block_on};
+
+    #[test]
+    fn bounds() {
+        let pool = BlockingPool::new("test-pool", 1, 1);
+        let token = CancellationToken::new();
+        let (started_tx, started) = std::sync::mpsc::channel();
+        let running = pool
+            .spawn({
+                let token = token.clone();
+                move || {
+                    started_tx
+                        .send(thread::current().name().map(String::from))
+                        .unwrap();
+                    simple_block_on(token.cancelled());
+                    1
+                }
+            })
+            .unwrap();
+        assert_eq!(started.recv().unwrap().as_deref(), Some("test-pool"));
+        let queued = pool.spawn(|| 2).unwrap();
+        assert_eq!(pool.spawn(|| 3).unwrap_err(), RuntimeError::LimitReached);
+        assert_eq!(
+            pool.stats(),
+            BlockingPoolStats {
+                threads: 1,
+                idle: 0,
+                queued: 1
+            }
+        );
+        token.cancel();
+        assert_eq!(simple_block_on(running), 1);
+        assert_eq!(simple_block_on(queued), 2);
+    }
+
+    #[test]
+    #[should_panic = "Blocking pools need at least one thread"]
+    fn zero_threads() {
+        BlockingPool::new("test-pool", 0, 1);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::util::{AbortHandle, AbortHandleImpl, JoinError, Task, TaskImpl};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::{self, Future},
+    pin::{Pin, pin},
+    sync::{
+        Arc, Mutex, MutexGuard, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// A token which can be used to cooperatively cancel a tree of tasks.
+///
+/// Canceling a token cancels all of its children tokens, but canceling a child token doesn't
+/// affect its parent.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<Node>);
+
+#[derive(Default)]
+struct Node {
+    cancelled: AtomicBool,
+    inner: Mutex<Inner>,
+    // Keeps the intermediate tokens alive so that their children still get canceled once they
+    // are dropped
+    _parent: Option<Arc<Node>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    waiters: HashMap<u64, Waker>,
+    next_waiter: u64,
+    children: Vec<Weak<Node>>,
+}
+
+impl Node {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let (waiters, children) = {
+            let mut inner = self.lock();
+            (
+                std::mem::take(&mut inner.waiters),
+                std::mem::take(&mut inner.children),
+            )
+        };
+        waiters.into_values().for_each(Waker::wake);
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+impl CancellationToken {
+    /// Create a new CancellationToken
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a child token, which gets canceled when this one is
+    pub fn child_token(&self) -> Self {
+        let child = Self(Arc::new(Node {
+            _parent: Some(self.0.clone()),
+            ..Node::default()
+        }));
+        let mut inner = self.0.lock();
+        if self.0.is_cancelled() {
+            child.0.cancelled.store(true, Ordering::Release);
+        } else {
+            inner.children.retain(|child| child.strong_count() > 0);
+            inner.children.push(Arc::downgrade(&child.0));
+        }
+        child
+    }
+
+    /// Cancel this token and all of its children
+    pub fn cancel(&self) {
+        self.0.cancel();
+    }
+
+    /// Check whether this token has been canceled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Wait for this token to be canceled
+    pub fn cancelled(&self) -> WaitForCancellation {
+        WaitForCancellation {
+            token: self.clone(),
+            waiter: None,
+        }
+    }
+
+    /// Run the given future until completion, or until this token gets canceled.
+    ///
+    /// Resolves to None if the token got canceled first.
+    pub async fn run_until_cancelled<F: Future>(&self, f: F) -> Option<F::Output> {
+        let mut f = pin!(f);
+        let mut cancelled = self.cancelled();
+        future::poll_fn(|cx| {
+            if Pin::new(&mut cancelled).poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            f.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled`
+#[derive(Debug)]
+pub struct WaitForCancellation {
+    token: CancellationToken,
+    waiter: Option<u64>,
+}
+
+impl Future for WaitForCancellation {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let node = self.token.0.clone();
+        if node.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let mut inner = node.lock();
+        // Check again with the lock held so that we cannot miss the wake up
+        if node.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let waiter = *self.waiter.get_or_insert_with(|| {
+            inner.next_waiter += 1;
+            inner.next_waiter
+        });
+        inner.waiters.insert(waiter, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for WaitForCancellation {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter {
+            self.token.0.lock().waiters.remove(&waiter);
+        }
+    }
+}
+
+/// A task running a blocking closure which can be canceled cooperatively, returned by
+/// `Executor::spawn_blocking_cancellable`.
+///
+/// Canceling or aborting it cancels the token given to the closure, `cancel` then waits for the
+/// closure to return and yields its output.
+#[derive(Debug)]
+pub struct CancellableTask<I: TaskImpl> {
+    task: Task<I>,
+    token: CancellationToken,
+}
+
+impl<I: TaskImpl> CancellableTask<I> {
+    pub(crate) fn new(task: Task<I>, token: CancellationToken) -> Self {
+        Self { task, token }
+    }
+}
+
+#[async_trait]
+impl<I: TaskImpl> TaskImpl for CancellableTask<I>
+where
+    <I as Future>::Output: Send,
+{
+    async fn cancel(&mut self) -> Option<<I as Future>::Output> {
+        self.token.cancel();
+        future::poll_fn(|cx| self.task.poll_try_join(cx)).await.ok()
+    }
+
+    fn poll_try_join(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<<I as Future>::Output, JoinError>> {
+        self.task.poll_try_join(cx)
+    }
+
+    fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+        Some(Arc::new(CancellableAbortHandle {
+            token: self.token.clone(),
+            inner: self.task.abort_handle(),
+        }))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    fn abort(&mut self) {
+        self.token.cancel();
+        self.task.abort();
+    }
+}
+
+impl<I: TaskImpl> Future for CancellableTask<I> {
+    type Output = <I as Future>::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.task).poll(cx)
+    }
+}
+
+#[derive(Debug)]
+struct CancellableAbortHandle {
+    token: CancellationToken,
+    inner: AbortHandle,
+}
+
+impl AbortHandleImpl for CancellableAbortHandle {
+    fn abort(&self) {
+        self.token.cancel();
+        self.inner.abort();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::simple_block_on;
+
+    #[test]
+    fn hierarchy() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+        child.cancel();
+        assert!(!parent.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        let other = parent.child_token();
+        let waiter = std::thread::spawn(move || simple_block_on(other.cancelled()));
+        parent.cancel();
+        waiter.join().unwrap();
+        assert!(parent.child_token().is_cancelled());
+    }
+
+    #[test]
+    fn dropped_intermediate_token() {
+        let parent = CancellationToken::new();
+        let grandchild = parent.child_token().child_token();
+        parent.cancel();
+        assert!(grandchild.is_cancelled());
+    }
+
+    #[test]
+    fn run_until_cancelled() {
+        let token = CancellationToken::new();
+        assert_eq!(
+            simple_block_on(token.run_until_cancelled(future::ready(42))),
+            Some(42)
+        );
+        token.cancel();
+        assert_eq!(
+            simple_block_on(token.run_until_cancelled(future::pending::<()>())),
+            None
+        );
+    }
+}
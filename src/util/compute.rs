@@ -0,0 +1,37 @@
+use crate::util::{PoolTask, Task};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::OnceLock;
+
+// Sized to the available cores, created on first use
+fn compute_pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        ThreadPoolBuilder::new()
+            .thread_name(|i| format!("async-rs-compute-{i}"))
+            .build()
+            .expect("failed to start the compute pool")
+    })
+}
+
+/// Run a CPU-bound closure on the compute pool
+pub(crate) fn spawn_compute<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+    f: F,
+) -> Task<PoolTask<T>> {
+    let (task, job) = PoolTask::new(f);
+    compute_pool().spawn(job);
+    task.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Runtime, traits::*};
+
+    #[test]
+    fn spawn_compute() {
+        let runtime = Runtime::noop();
+        let sum = runtime.spawn_compute(|| (1..=100u64).sum::<u64>());
+        assert_eq!(runtime.block_on(sum), 5050);
+        let panicked = runtime.spawn_compute(|| panic!("boom")).try_join();
+        assert!(runtime.block_on(panicked).is_err_and(|err| err.is_panic()));
+    }
+}
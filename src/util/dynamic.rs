@@ -0,0 +1,56 @@
+use crate::sys::AsSysFd;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    fmt,
+    io::{self, IoSlice, IoSliceMut, Read, Write},
+};
+
+/// A type-erased asynchronous IO handle, see `DynRuntime`
+pub trait AsyncIOHandle: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+impl<H: AsyncRead + AsyncWrite + Send + Unpin + 'static> AsyncIOHandle for H {}
+
+/// A boxed type-erased asynchronous IO handle
+pub type DynIO = Box<dyn AsyncIOHandle>;
+
+pub(crate) trait SysSocket: Read + Write + AsSysFd + Send + 'static {}
+impl<H: Read + Write + AsSysFd + Send + 'static> SysSocket for H {}
+
+/// A type-erased synchronous handle to register on a reactor, see `DynRuntime`
+pub struct DynSocket(pub(crate) Box<dyn SysSocket>);
+
+impl DynSocket {
+    /// Wrap a synchronous handle
+    pub fn new<H: Read + Write + AsSysFd + Send + 'static>(socket: H) -> Self {
+        Self(Box::new(socket))
+    }
+}
+
+impl Read for DynSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.0.read_vectored(bufs)
+    }
+}
+
+impl Write for DynSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl fmt::Debug for DynSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynSocket").finish()
+    }
+}
@@ -0,0 +1,106 @@
+use crate::util::{AbortHandleImpl, JoinError};
+use std::{
+    future::{self, Future},
+    panic::{self, AssertUnwindSafe},
+    pin::pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Poll, Waker},
+};
+
+/// Shared state between a task spawned through `joinable` and its abort handles
+#[derive(Debug, Default)]
+pub(crate) struct TaskState {
+    aborted: AtomicBool,
+    finished: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl TaskState {
+    fn aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut registered = self.waker.lock().unwrap_or_else(|e| e.into_inner());
+        if !registered.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            *registered = Some(waker.clone());
+        }
+    }
+}
+
+impl AbortHandleImpl for TaskState {
+    fn abort(&self) {
+        self.aborted.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            waker.wake();
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+}
+
+// Marks the task as finished once its future or closure is gone, whether it completed or got
+// dropped by the executor.
+struct FinishGuard(Arc<TaskState>);
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// Wrap a future so that its panics are caught and reported as a `JoinError`, and so that it
+/// can be aborted through its `TaskState`.
+///
+/// This is used by executors which propagate panics through their own task handles and cannot
+/// abort them from a shared handle, so that all of them behave the same.
+pub(crate) fn joinable<F: Future>(
+    f: F,
+    state: Arc<TaskState>,
+) -> impl Future<Output = Result<F::Output, JoinError>> {
+    // Create the guard eagerly so that the task counts as finished even if the executor drops it
+    // before its first poll
+    let guard = FinishGuard(state.clone());
+    async move {
+        let _guard = guard;
+        let mut f = pin!(f);
+        future::poll_fn(move |cx| {
+            // Register the waker before checking for abortion so that we cannot miss it
+            state.register(cx.waker());
+            if state.aborted() {
+                return Poll::Ready(Err(JoinError::Cancelled));
+            }
+            match panic::catch_unwind(AssertUnwindSafe(|| f.as_mut().poll(cx))) {
+                Ok(Poll::Pending) => Poll::Pending,
+                Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+                Err(payload) => Poll::Ready(Err(JoinError::Panicked(payload))),
+            }
+        })
+        .await
+    }
+}
+
+/// Wrap a blocking closure so that its panics are caught and reported as a `JoinError`, and so
+/// that it doesn't run if it got aborted before starting.
+pub(crate) fn joinable_blocking<T, F: FnOnce() -> T>(
+    f: F,
+    state: Arc<TaskState>,
+) -> impl FnOnce() -> Result<T, JoinError> {
+    let guard = FinishGuard(state.clone());
+    move || {
+        let _guard = guard;
+        if state.aborted() {
+            return Err(JoinError::Cancelled);
+        }
+        panic::catch_unwind(AssertUnwindSafe(f)).map_err(JoinError::Panicked)
+    }
+}
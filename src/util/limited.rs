@@ -0,0 +1,503 @@
+use crate::{
+    RuntimeError,
+    traits::{Capabilities, Executor, LocalExecutor},
+    util::{PoolTask, Task},
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+/// Executor wrapper capping the number of in-flight tasks, to apply backpressure.
+///
+/// `spawn` and `spawn_blocking` have separate caps. Once a cap is reached, `spawn_bounded` waits
+/// for a task to complete and `try_spawn` fails with `RuntimeError::LimitReached`. The plain
+/// `spawn` and `spawn_blocking` cannot wait, so the task they return waits for its turn before
+/// running: the work is bounded but the number of queued tasks isn't. Queued blocking closures
+/// don't hold a thread of the inner executor, they get run by the threads holding a slot once
+/// they're done.
+///
+/// Blocking closures are driven by a small task spawned on the inner executor, which is the one
+/// returned, so that they can be queued.
+///
+/// Clones share the same caps.
+#[derive(Clone, Debug)]
+pub struct Limited<E: Executor> {
+    executor: E,
+    tasks: Arc<Limit>,
+    blocking: Arc<Limit>,
+}
+
+impl<E: Executor> Limited<E> {
+    /// Wrap an Executor, allowing at most `max_tasks` futures and `max_blocking` blocking
+    /// closures to run at once
+    ///
+    /// # Panics
+    ///
+    /// Panics if either cap is zero, as nothing could ever run.
+    pub fn new(executor: E, max_tasks: usize, max_blocking: usize) -> Self {
+        Self {
+            executor,
+            tasks: Arc::new(Limit::new(max_tasks)),
+            blocking: Arc::new(Limit::new(max_blocking)),
+        }
+    }
+
+    /// The number of spawned futures which haven't completed yet
+    pub fn in_flight(&self) -> usize {
+        self.tasks.lock().in_flight
+    }
+
+    /// The number of spawned blocking closures which haven't completed yet
+    pub fn in_flight_blocking(&self) -> usize {
+        self.blocking.lock().in_flight
+    }
+
+    /// Spawn a future, waiting for an in-flight one to complete first if the cap is reached
+    pub async fn spawn_bounded<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<E::Task<T>> {
+        let permit = Limit::acquire(&self.tasks).await;
+        self.executor.spawn(with_permit(permit, f))
+    }
+
+    /// Spawn a blocking closure, waiting for an in-flight one to complete first if the cap is
+    /// reached
+    pub async fn spawn_blocking_bounded<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<E::Task<T>> {
+        let permit = Limit::acquire(&self.blocking).await;
+        let (task, job) = blocking_job(f);
+        self.executor.spawn_blocking(move || permit.run(job));
+        self.executor.spawn(join_blocking(task))
+    }
+}
+
+// Wrap a blocking closure into a job which can be queued, and the task getting its output
+fn blocking_job<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+    f: F,
+) -> (Task<PoolTask<T>>, Job) {
+    let (task, delivery) = PoolTask::pending();
+    (task.into(), Box::new(delivery.run_blocking(f)))
+}
+
+// Aborting the task returned to the caller aborts the job too, if it hasn't started yet
+async fn join_blocking<T: Send + 'static>(task: Task<PoolTask<T>>) -> T {
+    task.abort_on_drop().await
+}
+
+impl<E: Executor> Executor for Limited<E> {
+    type Task<T: Send + 'static> = E::Task<T>;
+
+    fn block_on<T, F: Future<Output = T>>(&self, f: F) -> T {
+        self.executor.block_on(f)
+    }
+
+    fn spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        let limit = self.tasks.clone();
+        self.executor.spawn(async move {
+            let permit = Limit::acquire(&limit).await;
+            with_permit(permit, f).await
+        })
+    }
+
+    fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        let (task, job) = blocking_job(f);
+        // Don't hold a blocking thread while waiting for our turn, queue the job instead
+        if let Some((permit, job)) = Limit::acquire_or_queue(&self.blocking, job) {
+            self.executor.spawn_blocking(move || permit.run(job));
+        }
+        self.executor.spawn(join_blocking(task))
+    }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        self.executor.try_block_on(f)
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let permit = Limit::try_acquire(&self.tasks).ok_or(RuntimeError::LimitReached)?;
+        self.executor.try_spawn(with_permit(permit, f))
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let permit = Limit::try_acquire(&self.blocking).ok_or(RuntimeError::LimitReached)?;
+        let (task, job) = blocking_job(f);
+        self.executor.try_spawn_blocking(move || permit.run(job))?;
+        self.executor.try_spawn(join_blocking(task))
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.executor.block_in_place(f)
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.executor.yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.executor.consume_budget()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        self.executor.executor_capabilities()
+    }
+}
+
+impl<E: LocalExecutor> LocalExecutor for Limited<E> {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        let limit = self.tasks.clone();
+        self.executor.spawn_local(async move {
+            let permit = Limit::acquire(&limit).await;
+            with_permit(permit, f).await
+        })
+    }
+
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        let permit = Limit::try_acquire(&self.tasks).ok_or(RuntimeError::LimitReached)?;
+        self.executor.try_spawn_local(with_permit(permit, f))
+    }
+}
+
+// The permit is moved into the future so that it gets released even if the task is dropped
+// before its first poll
+fn with_permit<F: Future>(permit: Permit, f: F) -> impl Future<Output = F::Output> {
+    async move {
+        let _permit = permit;
+        f.await
+    }
+}
+
+// A blocking closure waiting for a slot
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug)]
+struct Limit {
+    max: usize,
+    state: Mutex<LimitState>,
+}
+
+#[derive(Default)]
+struct LimitState {
+    in_flight: usize,
+    next_waiter: u64,
+    waiters: VecDeque<(u64, Waker)>,
+    queued: VecDeque<Job>,
+}
+
+impl fmt::Debug for LimitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LimitState")
+            .field("in_flight", &self.in_flight)
+            .field("waiters", &self.waiters.len())
+            .field("queued", &self.queued.len())
+            .finish()
+    }
+}
+
+impl Limit {
+    fn new(max: usize) -> Self {
+        assert!(max > 0, "Limited caps must be greater than zero");
+        Self {
+            max,
+            state: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimitState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn try_acquire(this: &Arc<Self>) -> Option<Permit> {
+        let mut state = this.lock();
+        if state.in_flight >= this.max || !state.waiters.is_empty() {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(Permit::new(this))
+    }
+
+    // Take a slot for the job right away, or queue it for the next permit holder to run it
+    fn acquire_or_queue(this: &Arc<Self>, job: Job) -> Option<(Permit, Job)> {
+        let mut state = this.lock();
+        if state.in_flight >= this.max || !state.waiters.is_empty() {
+            state.queued.push_back(job);
+            return None;
+        }
+        state.in_flight += 1;
+        Some((Permit::new(this), job))
+    }
+
+    fn acquire(this: &Arc<Self>) -> Acquire {
+        Acquire {
+            limit: this.clone(),
+            waiter: None,
+        }
+    }
+
+    // Wake the next waiter up if there is room for it
+    fn notify(&self, state: &mut LimitState) {
+        if state.in_flight < self.max
+            && let Some((_, waker)) = state.waiters.pop_front()
+        {
+            waker.wake();
+        }
+    }
+}
+
+// Releases its slot when dropped
+#[derive(Debug)]
+struct Permit {
+    limit: Arc<Limit>,
+    released: bool,
+}
+
+impl Permit {
+    fn new(limit: &Arc<Limit>) -> Self {
+        Self {
+            limit: limit.clone(),
+            released: false,
+        }
+    }
+
+    // Run a blocking job, then the ones queued in the meantime, before releasing the slot.
+    // Jobs catch their own panics.
+    fn run(mut self, mut job: Job) {
+        let limit = self.limit.clone();
+        loop {
+            job();
+            let mut state = limit.lock();
+            match state.queued.pop_front() {
+                Some(next) => job = next,
+                None => {
+                    self.release(&mut state);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn release(&mut self, state: &mut LimitState) {
+        state.in_flight -= 1;
+        self.limit.notify(state);
+        self.released = true;
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        let limit = self.limit.clone();
+        let mut state = limit.lock();
+        self.release(&mut state);
+        // Our job never ran, and nobody is left to run the queued ones: cancel them
+        let orphans = if state.in_flight == 0 {
+            std::mem::take(&mut state.queued)
+        } else {
+            VecDeque::new()
+        };
+        drop(state);
+        drop(orphans);
+    }
+}
+
+// Waits in line for a slot
+struct Acquire {
+    limit: Arc<Limit>,
+    waiter: Option<u64>,
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let limit = self.limit.clone();
+        let mut state = limit.lock();
+        let queued = self
+            .waiter
+            .and_then(|id| state.waiters.iter().position(|(waiter, _)| *waiter == id));
+        // Only take a slot when it's our turn
+        let our_turn = match (self.waiter, queued) {
+            (Some(_), Some(pos)) => pos == 0,
+            // We got woken up to take a slot
+            (Some(_), None) => true,
+            (None, _) => state.waiters.is_empty(),
+        };
+        if our_turn && state.in_flight < limit.max {
+            if let Some(pos) = queued {
+                state.waiters.remove(pos);
+            }
+            state.in_flight += 1;
+            self.waiter = None;
+            return Poll::Ready(Permit::new(&self.limit));
+        }
+        match (self.waiter, queued) {
+            (_, Some(pos)) => state.waiters[pos].1.clone_from(cx.waker()),
+            // Someone took our slot in the meantime, keep our place in line
+            (Some(id), None) => state.waiters.push_front((id, cx.waker().clone())),
+            (None, None) => {
+                let id = state.next_waiter;
+                state.next_waiter += 1;
+                state.waiters.push_back((id, cx.waker().clone()));
+                self.waiter = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        let Some(id) = self.waiter else {
+            return;
+        };
+        let mut state = self.limit.lock();
+        if let Some(pos) = state.waiters.iter().position(|(waiter, _)| *waiter == id) {
+            state.waiters.remove(pos);
+        } else {
+            // We got woken up but won't take the slot, pass it on
+            self.limit.notify(&mut state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Runtime,
+        util::{self, CancellationToken},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn caps() {
+        let limited = Limited::new(Runtime::noop(), 1, 1);
+        let _permit = Limit::try_acquire(&limited.tasks).unwrap();
+        let _blocking = Limit::try_acquire(&limited.blocking).unwrap();
+        assert_eq!(limited.in_flight(), 1);
+        assert_eq!(
+            limited.try_spawn(async {}).map(drop),
+            Err(RuntimeError::LimitReached)
+        );
+        assert_eq!(
+            limited.try_spawn_blocking(|| ()).map(drop),
+            Err(RuntimeError::LimitReached)
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn spawn_bounded() {
+        use crate::util::RuntimeParts;
+        let tokio = Runtime::tokio().unwrap();
+        let limited = Limited::new(tokio.clone(), 2, 1);
+        let runtime = Runtime::new(RuntimeParts::new(limited.clone(), tokio));
+        runtime.block_on(async {
+            let token = CancellationToken::new();
+            let first = runtime.try_spawn(token.cancelled()).unwrap();
+            let second = limited.spawn_bounded(token.cancelled()).await;
+            assert_eq!(
+                runtime.try_spawn(async {}).map(drop),
+                Err(RuntimeError::LimitReached)
+            );
+            let cancel = runtime.spawn_blocking(move || token.cancel());
+            let third = limited.spawn_bounded(async { 42 }).await;
+            assert_eq!(third.await, 42);
+            first.await;
+            second.await;
+            cancel.await;
+            assert_eq!(limited.in_flight(), 0);
+        });
+    }
+
+    #[test]
+    #[should_panic = "Limited caps must be greater than zero"]
+    fn zero_cap() {
+        Limited::new(Runtime::noop(), 1, 0);
+    }
+
+    #[test]
+    fn queued_blocking() {
+        let runtime = Runtime::builtin_with_threads(2).unwrap();
+        let limited = Limited::new(runtime.clone(), 4, 2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let tasks = (0..8)
+            .map(|i| {
+                let (running, max) = (running.clone(), max.clone());
+                limited.spawn_blocking(move || {
+                    max.fetch_max(running.fetch_add(1, Ordering::AcqRel) + 1, Ordering::AcqRel);
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    running.fetch_sub(1, Ordering::AcqRel);
+                    i
+                })
+            })
+            .collect::<Vec<_>>();
+        let sum = runtime.block_on(async {
+            let mut sum = 0;
+            for task in tasks {
+                sum += task.await;
+            }
+            sum
+        });
+        assert_eq!(sum, 28);
+        assert!(max.load(Ordering::Acquire) <= 2);
+    }
+
+    #[test]
+    fn acquire_in_order() {
+        let limit = Arc::new(Limit::new(1));
+        let token = CancellationToken::new();
+        let permit = Limit::try_acquire(&limit).unwrap();
+        let waiter = std::thread::spawn({
+            let limit = limit.clone();
+            let token = token.clone();
+            move || {
+                util::simple_block_on(async {
+                    let mut acquire = Limit::acquire(&limit);
+                    std::future::poll_fn(|cx| {
+                        assert!(Pin::new(&mut acquire).poll(cx).is_pending());
+                        token.cancel();
+                        Poll::Ready(())
+                    })
+                    .await;
+                    acquire.await
+                })
+            }
+        });
+        util::simple_block_on(token.cancelled());
+        // Someone is waiting in line, so we cannot jump ahead of them
+        assert!(Limit::try_acquire(&limit).is_none());
+        drop(permit);
+        let permit = waiter.join().unwrap();
+        assert_eq!(limit.lock().in_flight, 1);
+        drop(permit);
+        assert_eq!(limit.lock().in_flight, 0);
+    }
+}
@@ -6,24 +6,67 @@ pub use addr::*;
 mod block_on;
 pub use block_on::*;
 
+mod blocking_pool;
+pub use blocking_pool::*;
+
+mod cancellation;
+pub use cancellation::*;
+
+#[cfg(feature = "compute")]
+mod compute;
+#[cfg(feature = "compute")]
+pub(crate) use compute::*;
+
 mod dummy;
 pub use dummy::*;
 
+mod dynamic;
+pub use dynamic::*;
+
+mod joinable;
+pub(crate) use joinable::*;
+
 #[cfg(feature = "async-io")]
 mod io;
 #[cfg(feature = "async-io")]
 pub use io::*;
 
+mod limited;
+pub use limited::*;
+
+mod pool_task;
+pub use pool_task::*;
+
 mod runtime;
 pub use runtime::*;
 
+mod scope;
+pub use scope::*;
+
+#[cfg(feature = "tokio")]
+mod shutdown;
+#[cfg(feature = "tokio")]
+pub use shutdown::*;
+
 mod task;
 pub use task::*;
 
+mod task_group;
+pub use task_group::*;
+
+mod task_local;
+pub use task_local::*;
+
 #[cfg(feature = "tokio")]
 mod tokio;
 #[cfg(feature = "tokio")]
 pub use tokio::*;
 
+mod timer;
+pub use timer::*;
+
+mod yield_now;
+pub use yield_now::*;
+
 #[cfg(test)]
 pub(crate) mod test;
@@ -0,0 +1,147 @@
+use crate::util::{AbortHandleImpl, JoinError, TaskImpl, TaskState, joinable_blocking};
+use async_trait::async_trait;
+use std::{
+    fmt,
+    future::{self, Future},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+struct Slot<T> {
+    output: Option<Result<T, JoinError>>,
+    waker: Option<Waker>,
+}
+
+type SharedSlot<T> = Arc<Mutex<Slot<T>>>;
+
+/// A task whose output is delivered from another thread, such as the ones running on a
+/// `BlockingPool`
+pub struct PoolTask<T: Send + 'static> {
+    slot: SharedSlot<T>,
+    state: Arc<TaskState>,
+}
+
+impl<T: Send + 'static> PoolTask<T> {
+    /// Create a task along with the `Delivery` its output must go through
+    pub(crate) fn pending() -> (Self, Delivery<T>) {
+        let state = Arc::<TaskState>::default();
+        let slot = Arc::new(Mutex::new(Slot {
+            output: None,
+            waker: None,
+        }));
+        let delivery = Delivery {
+            slot: slot.clone(),
+            state: state.clone(),
+            delivered: false,
+        };
+        (Self { slot, state }, delivery)
+    }
+
+    /// Create a task for a blocking closure, returning it along with the job running the closure
+    #[cfg(feature = "compute")]
+    pub(crate) fn new<F: FnOnce() -> T + Send + 'static>(
+        f: F,
+    ) -> (Self, impl FnOnce() + Send + 'static) {
+        let (task, delivery) = Self::pending();
+        (task, delivery.run_blocking(f))
+    }
+}
+
+#[async_trait]
+impl<T: Send + 'static> TaskImpl for PoolTask<T> {
+    async fn cancel(&mut self) -> Option<T> {
+        // The output gets delivered either way, wait for it so that the task is done running
+        self.state.abort();
+        future::poll_fn(|cx| self.poll_try_join(cx)).await.ok()
+    }
+
+    fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, JoinError>> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+        Some(self.state.clone())
+    }
+}
+
+impl<T: Send + 'static> Future for PoolTask<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_try_join(cx)
+            .map(|res| res.unwrap_or_else(|err| err.resume()))
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for PoolTask<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolTask")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The sending half of a `PoolTask`.
+///
+/// If it gets dropped without delivering anything, the task completes as cancelled.
+pub(crate) struct Delivery<T> {
+    slot: SharedSlot<T>,
+    state: Arc<TaskState>,
+    delivered: bool,
+}
+
+impl<T: Send + 'static> Delivery<T> {
+    /// Wrap a blocking closure so that it delivers its output once done
+    pub(crate) fn run_blocking<F: FnOnce() -> T + Send + 'static>(
+        self,
+        f: F,
+    ) -> impl FnOnce() + Send + 'static {
+        let f = joinable_blocking(f, self.state.clone());
+        move || self.deliver(f())
+    }
+
+    /// Wrap a future so that it delivers its output once done
+    pub(crate) fn run<F: Future<Output = T>>(self, f: F) -> impl Future<Output = ()> {
+        let f = crate::util::joinable(f, self.state.clone());
+        async move { self.deliver(f.await) }
+    }
+
+    /// Complete the task with an error without running anything
+    #[cfg(feature = "sharded")]
+    pub(crate) fn fail(self, err: JoinError) {
+        self.state.finish();
+        self.deliver(Err(err));
+    }
+
+    fn deliver(mut self, output: Result<T, JoinError>) {
+        self.delivered = true;
+        self.store(output);
+    }
+}
+
+impl<T> Delivery<T> {
+    fn store(&self, output: Result<T, JoinError>) {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        slot.output = Some(output);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Delivery<T> {
+    fn drop(&mut self) {
+        if !self.delivered {
+            self.state.finish();
+            self.store(Err(JoinError::Cancelled));
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
+    RuntimeError,
     sys::AsSysFd,
-    traits::{Executor, Reactor, RuntimeKit},
+    traits::{Capabilities, Executor, LocalExecutor, Reactor, RuntimeKit},
     util::Task,
 };
 use futures_core::Stream;
@@ -27,7 +28,12 @@ impl<E: Executor, R: Reactor> RuntimeParts<E, R> {
     }
 }
 
-impl<E: Executor + fmt::Debug, R: Reactor + fmt::Debug> RuntimeKit for RuntimeParts<E, R> {}
+impl<
+    E: Executor + fmt::Debug + Send + Sync + 'static,
+    R: Reactor + fmt::Debug + Send + Sync + 'static,
+> RuntimeKit for RuntimeParts<E, R>
+{
+}
 
 impl<E: Executor, R: Reactor> Executor for RuntimeParts<E, R> {
     type Task<T: Send + 'static> = E::Task<T>;
@@ -49,6 +55,56 @@ impl<E: Executor, R: Reactor> Executor for RuntimeParts<E, R> {
     ) -> Task<Self::Task<T>> {
         self.executor.spawn_blocking(f)
     }
+
+    fn try_block_on<T, F: Future<Output = T>>(&self, f: F) -> Result<T, RuntimeError> {
+        self.executor.try_block_on(f)
+    }
+
+    fn try_spawn<T: Send + 'static, F: Future<Output = T> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.executor.try_spawn(f)
+    }
+
+    fn try_spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.executor.try_spawn_blocking(f)
+    }
+
+    fn block_in_place<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.executor.block_in_place(f)
+    }
+
+    fn yield_now(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.executor.yield_now()
+    }
+
+    fn consume_budget(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.executor.consume_budget()
+    }
+
+    fn executor_capabilities(&self) -> Capabilities {
+        self.executor.executor_capabilities()
+    }
+}
+
+impl<E: LocalExecutor, R: Reactor> LocalExecutor for RuntimeParts<E, R> {
+    fn spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Task<Self::Task<T>> {
+        self.executor.spawn_local(f)
+    }
+
+    fn try_spawn_local<T: Send + 'static, F: Future<Output = T> + 'static>(
+        &self,
+        f: F,
+    ) -> Result<Task<Self::Task<T>>, RuntimeError> {
+        self.executor.try_spawn_local(f)
+    }
 }
 
 impl<E: Executor, R: Reactor> Reactor for RuntimeParts<E, R> {
@@ -76,4 +132,8 @@ impl<E: Executor, R: Reactor> Reactor for RuntimeParts<E, R> {
     ) -> impl Future<Output = io::Result<Self::TcpStream>> + Send + 'static {
         self.reactor.tcp_connect_addr(addr)
     }
+
+    fn reactor_capabilities(&self) -> Capabilities {
+        self.reactor.reactor_capabilities()
+    }
 }
@@ -0,0 +1,271 @@
+use std::{
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+type Child<'env> = Pin<Box<dyn Future<Output = ()> + Send + 'env>>;
+
+struct State<'env> {
+    spawned: Vec<Child<'env>>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// Create a new scope in which futures borrowing from the enclosing stack frame can be spawned.
+///
+/// See `Runtime::scope` for details.
+pub fn scope<'env, F: FnOnce(Scope<'env>) -> Fut, Fut: Future + 'env>(
+    f: F,
+) -> ScopeFuture<'env, Fut> {
+    let scope = Scope {
+        state: Arc::new(Mutex::new(State {
+            spawned: Vec::new(),
+            waker: None,
+            closed: false,
+        })),
+    };
+    ScopeFuture {
+        body: Some(Box::pin(f(scope.clone()))),
+        output: None,
+        children: Vec::new(),
+        scope,
+    }
+}
+
+/// A handle to spawn futures borrowing from the enclosing stack frame, see `Runtime::scope`
+#[derive(Clone)]
+pub struct Scope<'env> {
+    state: Arc<Mutex<State<'env>>>,
+}
+
+impl<'env> Scope<'env> {
+    /// Spawn a future in this scope and return a handle to await its output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scope already completed.
+    pub fn spawn<T: Send + 'env, F: Future<Output = T> + Send + 'env>(
+        &self,
+        f: F,
+    ) -> ScopedTask<T> {
+        let slot = Arc::new(Mutex::new(Slot {
+            output: None,
+            waker: None,
+        }));
+        let child_slot = slot.clone();
+        let child = Box::pin(async move {
+            let output = f.await;
+            let mut slot = child_slot.lock().unwrap_or_else(|e| e.into_inner());
+            slot.output = Some(output);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        });
+        let mut state = self.lock();
+        assert!(
+            !state.closed,
+            "Cannot spawn on a scope which already completed"
+        );
+        state.spawned.push(child);
+        if let Some(waker) = state.waker.as_ref() {
+            waker.wake_by_ref();
+        }
+        ScopedTask(slot)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<'env>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for Scope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope").finish()
+    }
+}
+
+/// The future driving a scope and all of its children, returned by `Runtime::scope`.
+///
+/// Resolves to the output of the scope body once it and all the children completed.
+/// Dropping it cancels all the children.
+pub struct ScopeFuture<'env, Fut: Future> {
+    body: Option<Pin<Box<Fut>>>,
+    output: Option<Fut::Output>,
+    children: Vec<Child<'env>>,
+    scope: Scope<'env>,
+}
+
+impl<Fut: Future> Unpin for ScopeFuture<'_, Fut> {}
+
+impl<Fut: Future> Future for ScopeFuture<'_, Fut> {
+    type Output = Fut::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(body) = this.body.as_mut()
+            && let Poll::Ready(output) = body.as_mut().poll(cx)
+        {
+            this.output = Some(output);
+            this.body = None;
+        }
+        this.children
+            .retain_mut(|child| child.as_mut().poll(cx).is_pending());
+        // Children may spawn other children while being polled, keep going until we're done
+        loop {
+            let mut spawned = {
+                let mut state = this.scope.lock();
+                if !state
+                    .waker
+                    .as_ref()
+                    .is_some_and(|w| w.will_wake(cx.waker()))
+                {
+                    state.waker = Some(cx.waker().clone());
+                }
+                mem::take(&mut state.spawned)
+            };
+            if spawned.is_empty() {
+                break;
+            }
+            spawned.retain_mut(|child| child.as_mut().poll(cx).is_pending());
+            this.children.append(&mut spawned);
+        }
+        if this.body.is_none() && this.children.is_empty() {
+            this.scope.lock().closed = true;
+            Poll::Ready(this.output.take().expect("scope polled after completion"))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<Fut: Future> Drop for ScopeFuture<'_, Fut> {
+    fn drop(&mut self) {
+        // Children may hold the scope, break the cycle by dropping them outside of the lock
+        let spawned = {
+            let mut state = self.scope.lock();
+            state.closed = true;
+            state.waker = None;
+            mem::take(&mut state.spawned)
+        };
+        drop(spawned);
+    }
+}
+
+impl<Fut: Future> fmt::Debug for ScopeFuture<'_, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeFuture")
+            .field("body_completed", &self.body.is_none())
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+struct Slot<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// A handle to await the output of a future spawned with `Scope::spawn`
+pub struct ScopedTask<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T> Future for ScopedTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for ScopedTask<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ScopedTask").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::simple_block_on;
+    use std::{future, sync::atomic::AtomicUsize, sync::atomic::Ordering};
+
+    #[test]
+    fn borrow() {
+        let data = vec![1, 2, 3];
+        let count = AtomicUsize::new(0);
+        let (data, count_ref) = (&data, &count);
+        let sum = simple_block_on(scope(|s| async move {
+            let first = s.spawn(async move { data[0] });
+            for _ in data {
+                let s2 = s.clone();
+                s.spawn(async move {
+                    // Spawn from a child, and yield once so that we get polled again
+                    s2.spawn(async move { count_ref.fetch_add(1, Ordering::SeqCst) });
+                    let mut yielded = false;
+                    future::poll_fn(|cx| {
+                        if yielded {
+                            return Poll::Ready(());
+                        }
+                        yielded = true;
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    })
+                    .await;
+                    count_ref.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+            first.await + data.iter().sum::<i32>()
+        }));
+        assert_eq!(sum, 7);
+        assert_eq!(count.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn drop_cancels_children() {
+        struct Guard<'a>(&'a AtomicUsize);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let dropped = AtomicUsize::new(0);
+        let dropped_ref = &dropped;
+        let mut scope = scope(|s| async move {
+            for _ in 0..2 {
+                let guard = Guard(dropped_ref);
+                s.spawn(async move {
+                    let _guard = guard;
+                    future::pending::<()>().await
+                });
+            }
+            future::pending::<()>().await
+        });
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut scope).poll(&mut cx).is_pending());
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+        drop(scope);
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn child_panic() {
+        let res = std::panic::catch_unwind(|| {
+            simple_block_on(scope(|s| async move {
+                s.spawn(async { panic!("boom") }).await;
+            }))
+        });
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::util::{AbortHandleImpl, CancellationToken, Timer};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// What happened to the running tasks during a runtime shutdown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// The number of tasks which completed before the deadline
+    pub completed: usize,
+    /// The number of tasks which were still running at the deadline and got aborted
+    pub aborted: usize,
+    /// The number of blocking tasks which were still running at the deadline. They cannot be
+    /// aborted and keep running in the background.
+    pub detached: usize,
+}
+
+#[derive(Default)]
+struct State {
+    next: u64,
+    tasks: HashMap<u64, Tracked>,
+    closed: bool,
+}
+
+struct Tracked {
+    handle: Option<Arc<dyn AbortHandleImpl>>,
+    blocking: bool,
+}
+
+/// Keeps track of the running tasks of a runtime so that they can be drained on shutdown
+#[derive(Default)]
+pub(crate) struct TaskTracker {
+    state: Mutex<State>,
+    drained: Condvar,
+    // Canceled once closed without any task left
+    idle: CancellationToken,
+}
+
+impl TaskTracker {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start tracking a new task, returns None if the tracker is closed.
+    ///
+    /// The task is tracked until the returned guard is dropped. Blocking tasks cannot be aborted,
+    /// they are reported separately.
+    pub(crate) fn track(self: &Arc<Self>, blocking: bool) -> Option<TrackGuard> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        state.next += 1;
+        let key = state.next;
+        state.tasks.insert(
+            key,
+            Tracked {
+                handle: None,
+                blocking,
+            },
+        );
+        Some(TrackGuard {
+            tracker: self.clone(),
+            key,
+        })
+    }
+
+    /// Attach the handle used to abort a tracked task.
+    ///
+    /// If the task is gone already, it either completed or got drained before its handle
+    /// existed, in which case it gets aborted right away.
+    pub(crate) fn set_abort_handle(&self, key: u64, handle: Arc<dyn AbortHandleImpl>) {
+        match self.lock().tasks.get_mut(&key) {
+            Some(tracked) => tracked.handle = Some(handle),
+            None => handle.abort(),
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Stop accepting new tasks, returning the number of running ones
+    pub(crate) fn close(&self) -> usize {
+        let mut state = self.lock();
+        state.closed = true;
+        if state.tasks.is_empty() {
+            self.idle.cancel();
+        }
+        state.tasks.len()
+    }
+
+    /// Wait for the tasks which were running when closing up to `timeout` and abort the rest
+    pub(crate) fn drain(&self, running: usize, timeout: Duration) -> ShutdownReport {
+        let state = self.lock();
+        let (state, _) = self
+            .drained
+            .wait_timeout_while(state, timeout, |state| !state.tasks.is_empty())
+            .unwrap_or_else(|e| e.into_inner());
+        drop(state);
+        self.abort_remaining(running)
+    }
+
+    /// Asynchronously wait for the tasks which were running when closing up to `timeout` and
+    /// abort the rest
+    pub(crate) async fn drain_async(&self, running: usize, timeout: Duration) -> ShutdownReport {
+        self.idle.run_until_cancelled(Timer::after(timeout)).await;
+        self.abort_remaining(running)
+    }
+
+    fn abort_remaining(&self, running: usize) -> ShutdownReport {
+        let remaining = std::mem::take(&mut self.lock().tasks);
+        let mut report = ShutdownReport {
+            completed: running - remaining.len(),
+            ..ShutdownReport::default()
+        };
+        for tracked in remaining.values() {
+            if tracked.blocking {
+                report.detached += 1;
+            } else {
+                report.aborted += 1;
+            }
+            if let Some(handle) = tracked.handle.as_ref() {
+                handle.abort();
+            }
+        }
+        report
+    }
+}
+
+impl fmt::Debug for TaskTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("TaskTracker")
+            .field("running", &state.tasks.len())
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+/// Stops tracking a task when dropped, see `TaskTracker::track`
+pub(crate) struct TrackGuard {
+    tracker: Arc<TaskTracker>,
+    key: u64,
+}
+
+impl TrackGuard {
+    pub(crate) fn key(&self) -> u64 {
+        self.key
+    }
+}
+
+impl Drop for TrackGuard {
+    fn drop(&mut self) {
+        let mut state = self.tracker.lock();
+        if state.tasks.remove(&self.key).is_some() && state.tasks.is_empty() {
+            self.tracker.drained.notify_all();
+            if state.closed {
+                self.tracker.idle.cancel();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::simple_block_on;
+
+    #[test]
+    fn shutdown() {
+        let tracker = Arc::new(TaskTracker::default());
+        let done = tracker.track(false).unwrap();
+        let _stuck = tracker.track(false).unwrap();
+        let _blocking = tracker.track(true).unwrap();
+        let running = tracker.close();
+        std::thread::spawn(move || drop(done));
+        let report = tracker.drain(running, Duration::from_millis(50));
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 1,
+                aborted: 1,
+                detached: 1,
+            }
+        );
+        assert!(tracker.track(false).is_none());
+    }
+
+    #[test]
+    fn drain_async() {
+        let tracker = Arc::new(TaskTracker::default());
+        let done = tracker.track(false).unwrap();
+        let running = tracker.close();
+        std::thread::spawn(move || drop(done));
+        // Completes as soon as the last task is done rather than at the deadline
+        let report = simple_block_on(tracker.drain_async(running, Duration::from_secs(60)));
+        assert_eq!(report.completed, 1);
+    }
+
+    #[test]
+    fn late_abort_handle() {
+        #[derive(Debug, Default)]
+        struct Handle(std::sync::atomic::AtomicBool);
+
+        impl AbortHandleImpl for Handle {
+            fn abort(&self) {
+                self.0.store(true, std::sync::atomic::Ordering::Release);
+            }
+
+            fn is_finished(&self) -> bool {
+                false
+            }
+        }
+
+        let tracker = Arc::new(TaskTracker::default());
+        let guard = tracker.track(false).unwrap();
+        let running = tracker.close();
+        assert_eq!(tracker.drain(running, Duration::ZERO).aborted, 1);
+        let handle = Arc::new(Handle::default());
+        tracker.set_abort_handle(guard.key(), handle.clone());
+        assert!(handle.0.load(std::sync::atomic::Ordering::Acquire));
+    }
+}
@@ -1,24 +1,81 @@
 use async_trait::async_trait;
 use std::{
-    future::Future,
+    any::Any,
+    error::Error,
+    fmt,
+    future::{self, Future},
+    num::NonZeroU64,
+    panic,
     pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
     task::{Context, Poll},
 };
 
 /// A wrapper around implementation-specific tasks that implement the TaskImpl trait
 #[derive(Debug)]
-pub struct Task<I: TaskImpl>(I);
+pub struct Task<I: TaskImpl> {
+    inner: I,
+    id: TaskId,
+}
 
 impl<I: TaskImpl> Task<I> {
     /// Cancel the task, returning data if it was already finished
     pub async fn cancel(&mut self) -> Option<<Self as Future>::Output> {
-        self.0.cancel().await
+        self.inner.cancel().await
+    }
+
+    /// Get the unique identifier of this task
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Check whether the task has finished running
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    /// Turn this task into a guard which aborts it when dropped, instead of detaching it.
+    pub fn abort_on_drop(self) -> AbortOnDrop<I> {
+        AbortOnDrop(Some(self))
+    }
+
+    /// Get a cloneable handle that can be used to abort the task from anywhere
+    pub fn abort_handle(&self) -> AbortHandle {
+        AbortHandle {
+            id: self.id,
+            inner: self.inner.abort_handle(),
+        }
+    }
+
+    /// Wait for the task completion without propagating its panic.
+    ///
+    /// Resolves to `Err(JoinError::Panicked(payload))` if the task panicked, or to
+    /// `Err(JoinError::Cancelled)` if it got canceled before completing.
+    pub fn try_join(self) -> TryJoin<I> {
+        TryJoin(self)
+    }
+
+    pub(crate) fn abort(&mut self) {
+        self.inner.abort();
+    }
+
+    pub(crate) fn poll_try_join(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<<I as Future>::Output, JoinError>> {
+        self.inner.poll_try_join(cx)
     }
 }
 
 impl<I: TaskImpl> From<I> for Task<I> {
     fn from(task_impl: I) -> Self {
-        Self(task_impl)
+        Self {
+            inner: task_impl,
+            id: TaskId::next(),
+        }
     }
 }
 
@@ -26,13 +83,145 @@ impl<I: TaskImpl> Future for Task<I> {
     type Output = <I as Future>::Output;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Pin::new(&mut self.0).poll(cx)
+        Pin::new(&mut self.inner).poll(cx)
     }
 }
 
 impl<I: TaskImpl> Drop for Task<I> {
     fn drop(&mut self) {
-        self.0.detach();
+        self.inner.detach();
+    }
+}
+
+/// A guard around a Task which aborts it when dropped, returned by `Task::abort_on_drop`
+#[derive(Debug)]
+pub struct AbortOnDrop<I: TaskImpl>(Option<Task<I>>);
+
+impl<I: TaskImpl> AbortOnDrop<I> {
+    /// Get back the inner Task, which will be detached instead of aborted when dropped
+    pub fn into_task(mut self) -> Task<I> {
+        self.0.take().expect("task already taken")
+    }
+
+    fn task(&self) -> &Task<I> {
+        self.0.as_ref().expect("task already taken")
+    }
+
+    fn task_mut(&mut self) -> &mut Task<I> {
+        self.0.as_mut().expect("task already taken")
+    }
+
+    /// Cancel the task, returning data if it was already finished
+    pub async fn cancel(&mut self) -> Option<<Self as Future>::Output> {
+        self.task_mut().cancel().await
+    }
+
+    /// Wait for the task completion without propagating its panic, see `Task::try_join`.
+    ///
+    /// The task is still aborted if the returned future gets dropped before completion.
+    pub async fn try_join(mut self) -> Result<<Self as Future>::Output, JoinError> {
+        future::poll_fn(|cx| self.task_mut().poll_try_join(cx)).await
+    }
+
+    /// Get the unique identifier of this task
+    pub fn id(&self) -> TaskId {
+        self.task().id()
+    }
+
+    /// Check whether the task has finished running
+    pub fn is_finished(&self) -> bool {
+        self.task().is_finished()
+    }
+
+    /// Get a cloneable handle that can be used to abort the task from anywhere
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.task().abort_handle()
+    }
+}
+
+impl<I: TaskImpl> Future for AbortOnDrop<I> {
+    type Output = <I as Future>::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(self.task_mut()).poll(cx)
+    }
+}
+
+impl<I: TaskImpl> Drop for AbortOnDrop<I> {
+    fn drop(&mut self) {
+        if let Some(task) = self.0.as_mut() {
+            task.inner.abort();
+        }
+    }
+}
+
+/// A unique identifier for a Task
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(NonZeroU64);
+
+impl TaskId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(
+            NonZeroU64::new(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+                .expect("task id counter overflowed"),
+        )
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A cloneable handle which can be used to abort a Task from anywhere
+#[derive(Debug, Clone)]
+pub struct AbortHandle {
+    id: TaskId,
+    inner: Option<Arc<dyn AbortHandleImpl>>,
+}
+
+impl AbortHandle {
+    /// Request the task to be aborted.
+    ///
+    /// The task stops the next time it would be polled, awaiting it afterwards yields a
+    /// `JoinError::Cancelled`.
+    pub fn abort(&self) {
+        if let Some(inner) = self.inner.as_ref() {
+            inner.abort();
+        }
+    }
+
+    /// Check whether the task has finished running
+    pub fn is_finished(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_finished())
+    }
+
+    /// Get the unique identifier of the task
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+}
+
+/// A common interface to abort a task through a shared handle
+pub trait AbortHandleImpl: Send + Sync + fmt::Debug + 'static {
+    /// Request the task to be aborted
+    fn abort(&self);
+
+    /// Check whether the task has finished running
+    fn is_finished(&self) -> bool;
+}
+
+/// Future returned by `Task::try_join`
+#[derive(Debug)]
+pub struct TryJoin<I: TaskImpl>(Task<I>);
+
+impl<I: TaskImpl> Future for TryJoin<I> {
+    type Output = Result<<I as Future>::Output, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_try_join(cx)
     }
 }
 
@@ -56,4 +245,138 @@ pub trait TaskImpl: Future + Send + Unpin + 'static {
         Self: Sized,
     {
     }
+
+    /// Poll the task for completion, reporting panics and cancellation as a `JoinError` instead
+    /// of propagating them.
+    ///
+    /// The default implementation cannot catch anything and defers to `Future::poll`.
+    fn poll_try_join(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<<Self as Future>::Output, JoinError>> {
+        Pin::new(self).poll(cx).map(Ok)
+    }
+
+    /// Get a shared handle which can abort the task from anywhere.
+    ///
+    /// The default implementation returns None, meaning the task cannot be aborted.
+    fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+        None
+    }
+
+    /// Check whether the task has finished running
+    fn is_finished(&self) -> bool {
+        self.abort_handle()
+            .is_some_and(|handle| handle.is_finished())
+    }
+
+    /// Abort the task without waiting for it to stop running.
+    ///
+    /// The default implementation goes through the abort handle, if any.
+    fn abort(&mut self) {
+        if let Some(handle) = self.abort_handle() {
+            handle.abort();
+        }
+    }
+}
+
+/// The type-erased output of a `DynTaskImpl`
+pub type DynOutput = Box<dyn Any + Send>;
+
+/// An object-safe interface to a Task whose output has been type-erased, see `DynRuntime`.
+///
+/// Dropping it detaches the task.
+pub trait DynTaskImpl: Send + 'static {
+    /// Poll the task for completion, see `TaskImpl::poll_try_join`
+    fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<DynOutput, JoinError>>;
+
+    /// Cancel the task, see `TaskImpl::cancel`
+    fn cancel(&mut self) -> Pin<Box<dyn Future<Output = Option<DynOutput>> + Send + '_>>;
+
+    /// Get a shared handle which can abort the task from anywhere
+    fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>>;
+
+    /// Check whether the task has finished running
+    fn is_finished(&self) -> bool;
+
+    /// Abort the task without waiting for it to stop running
+    fn abort(&mut self);
+}
+
+impl<I: TaskImpl<Output = DynOutput>> DynTaskImpl for Task<I> {
+    fn poll_try_join(&mut self, cx: &mut Context<'_>) -> Poll<Result<DynOutput, JoinError>> {
+        self.inner.poll_try_join(cx)
+    }
+
+    fn cancel(&mut self) -> Pin<Box<dyn Future<Output = Option<DynOutput>> + Send + '_>> {
+        self.inner.cancel()
+    }
+
+    fn abort_handle(&self) -> Option<Arc<dyn AbortHandleImpl>> {
+        self.inner.abort_handle()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    fn abort(&mut self) {
+        self.inner.abort();
+    }
+}
+
+/// The reason why a Task didn't complete
+#[derive(Debug)]
+pub enum JoinError {
+    /// The task was canceled before completing
+    Cancelled,
+    /// The task panicked, the panic payload is attached
+    Panicked(Box<dyn Any + Send + 'static>),
+}
+
+impl JoinError {
+    /// Whether the task was canceled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled)
+    }
+
+    /// Whether the task panicked
+    pub fn is_panic(&self) -> bool {
+        matches!(self, Self::Panicked(_))
+    }
+
+    /// Get the panic payload if the task panicked, or get the error back otherwise
+    pub fn try_into_panic(self) -> Result<Box<dyn Any + Send + 'static>, Self> {
+        match self {
+            Self::Panicked(payload) => Ok(payload),
+            err => Err(err),
+        }
+    }
+
+    /// Re-raise the panic if the task panicked, or panic because it got canceled
+    pub fn resume(self) -> ! {
+        match self {
+            Self::Cancelled => panic!("task has been canceled"),
+            Self::Panicked(payload) => panic::resume_unwind(payload),
+        }
+    }
 }
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => f.write_str("task has been canceled"),
+            Self::Panicked(payload) => {
+                if let Some(msg) = payload.downcast_ref::<&str>() {
+                    write!(f, "task panicked: {msg}")
+                } else if let Some(msg) = payload.downcast_ref::<String>() {
+                    write!(f, "task panicked: {msg}")
+                } else {
+                    f.write_str("task panicked")
+                }
+            }
+        }
+    }
+}
+
+impl Error for JoinError {}
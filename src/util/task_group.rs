@@ -0,0 +1,306 @@
+use crate::{
+    traits::Executor,
+    util::{AbortHandle, CancellationToken, JoinError, Task, TaskImpl},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::{self, Future},
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Wake, Waker},
+};
+
+/// A group of tasks spawned on the same executor, which can be joined in completion order.
+///
+/// All the tasks still running are aborted when the group is dropped.
+pub struct TaskGroup<E: Executor, T: Send + 'static> {
+    executor: E,
+    tasks: HashMap<u64, Entry<E::Task<Option<T>>>>,
+    next_id: u64,
+    ready: Arc<ReadyQueue>,
+    is_failure: Option<fn(&T) -> bool>,
+    // Canceled as soon as a task fails in fail-fast mode
+    failed: CancellationToken,
+}
+
+struct Entry<I: TaskImpl> {
+    task: Task<I>,
+    waker: Waker,
+}
+
+// The ids of the tasks woken up since they were last polled
+#[derive(Default)]
+struct ReadyQueue {
+    state: Mutex<ReadyState>,
+}
+
+#[derive(Default)]
+struct ReadyState {
+    ids: VecDeque<u64>,
+    waker: Option<Waker>,
+}
+
+struct TaskWaker {
+    id: u64,
+    ready: Arc<ReadyQueue>,
+}
+
+impl ReadyQueue {
+    fn lock(&self) -> MutexGuard<'_, ReadyState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, id: u64) {
+        let waker = {
+            let mut state = self.lock();
+            state.ids.push_back(id);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.push(self.id);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.push(self.id);
+    }
+}
+
+impl<E: Executor, T: Send + 'static> TaskGroup<E, T> {
+    /// Create a new empty TaskGroup spawning its tasks on the given executor
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor,
+            tasks: HashMap::new(),
+            next_id: 0,
+            ready: Arc::default(),
+            is_failure: None,
+            failed: CancellationToken::new(),
+        }
+    }
+
+    /// Spawn a future in this group
+    pub fn spawn<F: Future<Output = T> + Send + 'static>(&mut self, f: F) -> AbortHandle {
+        let task = match self.is_failure {
+            Some(is_failure) => {
+                let failed = self.failed.clone();
+                self.executor.spawn(async move {
+                    let output = failed.run_until_cancelled(f).await?;
+                    if is_failure(&output) {
+                        failed.cancel();
+                    }
+                    Some(output)
+                })
+            }
+            None => self.executor.spawn(async move { Some(f.await) }),
+        };
+        self.push(task)
+    }
+
+    /// Spawn a blocking task in this group
+    pub fn spawn_blocking<F: FnOnce() -> T + Send + 'static>(&mut self, f: F) -> AbortHandle {
+        let failed = self.failed.clone();
+        let is_failure = self.is_failure;
+        self.push(self.executor.spawn_blocking(move || {
+            // The closure cannot be interrupted, skip it if another task failed before it started
+            if failed.is_cancelled() {
+                return None;
+            }
+            let output = f();
+            if is_failure.is_some_and(|is_failure| is_failure(&output)) {
+                failed.cancel();
+            }
+            Some(output)
+        }))
+    }
+
+    fn push(&mut self, task: Task<E::Task<Option<T>>>) -> AbortHandle {
+        let handle = task.abort_handle();
+        let id = self.next_id;
+        self.next_id += 1;
+        let waker = Waker::from(Arc::new(TaskWaker {
+            id,
+            ready: self.ready.clone(),
+        }));
+        self.tasks.insert(id, Entry { task, waker });
+        // Poll it at least once
+        self.ready.push(id);
+        handle
+    }
+
+    /// Get the number of tasks in this group which haven't been joined yet
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Check whether there is no task left to join in this group
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Abort all the tasks of this group.
+    ///
+    /// The tasks stay in the group, joining them yields `JoinError::Cancelled` unless they
+    /// completed before being aborted.
+    pub fn abort_all(&mut self) {
+        for entry in self.tasks.values_mut() {
+            entry.task.abort();
+        }
+    }
+
+    /// Wait for the next task of this group to complete.
+    ///
+    /// Returns None if the group is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        future::poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    /// Poll for the next task of this group to complete.
+    ///
+    /// Only the tasks which got woken up since they were last polled are polled again.
+    ///
+    /// Returns `Poll::Ready(None)` if the group is empty.
+    pub fn poll_join_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<T, JoinError>>> {
+        if self.tasks.is_empty() {
+            return Poll::Ready(None);
+        }
+        // Register the waker first so that we cannot miss a task being woken up
+        self.ready.lock().waker = Some(cx.waker().clone());
+        loop {
+            let Some(id) = self.ready.lock().ids.pop_front() else {
+                return Poll::Pending;
+            };
+            // Tasks get woken up again after being joined sometimes, skip them
+            let Some(entry) = self.tasks.get_mut(&id) else {
+                continue;
+            };
+            let Poll::Ready(res) = entry
+                .task
+                .poll_try_join(&mut Context::from_waker(&entry.waker))
+            else {
+                continue;
+            };
+            self.tasks.remove(&id);
+            let res = res.and_then(|output| output.ok_or(JoinError::Cancelled));
+            if let (Ok(output), Some(is_failure)) = (res.as_ref(), self.is_failure)
+                && is_failure(output)
+            {
+                // The task already canceled the others, unless it was spawned before fail_fast
+                self.failed.cancel();
+                self.abort_all();
+            }
+            return Poll::Ready(Some(res));
+        }
+    }
+
+    /// Wait for all the tasks of this group to complete, in completion order
+    pub async fn join_all(mut self) -> Vec<Result<T, JoinError>> {
+        let mut results = Vec::with_capacity(self.len());
+        while let Some(res) = self.join_next().await {
+            results.push(res);
+        }
+        results
+    }
+}
+
+impl<E: Executor, O: Send + 'static, Err: Send + 'static> TaskGroup<E, Result<O, Err>> {
+    /// Cancel all the remaining tasks as soon as one of them returns an error.
+    ///
+    /// Tasks spawned once a task failed are canceled right away.
+    pub fn fail_fast(mut self) -> Self {
+        self.is_failure = Some(Result::is_err);
+        self
+    }
+}
+
+impl<E: Executor, T: Send + 'static> Drop for TaskGroup<E, T> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+impl<E: Executor + fmt::Debug, T: Send + 'static> fmt::Debug for TaskGroup<E, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskGroup")
+            .field("executor", &self.executor)
+            .field("len", &self.len())
+            .field("fail_fast", &self.is_failure.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Runtime,
+        traits::{Reactor, RuntimeKit},
+    };
+    use std::time::Duration;
+
+    fn completion_order<RK: RuntimeKit>(runtime: Runtime<RK>) {
+        runtime.block_on(async {
+            let mut group = TaskGroup::new(&runtime);
+            for delay in [60, 10, 30] {
+                let runtime = runtime.clone();
+                group.spawn(async move {
+                    runtime.sleep(Duration::from_millis(delay)).await;
+                    delay
+                });
+            }
+            assert_eq!(group.len(), 3);
+            let results = group
+                .join_all()
+                .await
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>();
+            assert_eq!(results, [10, 30, 60]);
+        });
+    }
+
+    fn fail_fast<RK: RuntimeKit>(runtime: Runtime<RK>) {
+        runtime.block_on(async {
+            let mut group = TaskGroup::new(&runtime).fail_fast();
+            let pending = group.spawn(std::future::pending::<Result<(), ()>>());
+            group.spawn(async { Err(()) });
+            // The failure cancels the other tasks without waiting for it to be joined
+            while !pending.is_finished() {
+                runtime.sleep(Duration::from_millis(1)).await;
+            }
+            let results = group.join_all().await;
+            assert!(results.iter().any(|res| matches!(res, Ok(Err(())))));
+            assert!(
+                results
+                    .iter()
+                    .any(|res| res.as_ref().is_err_and(JoinError::is_cancelled))
+            );
+        });
+    }
+
+    #[test]
+    fn builtin() {
+        completion_order(Runtime::builtin_with_threads(2).unwrap());
+        fail_fast(Runtime::builtin_with_threads(2).unwrap());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio() {
+        completion_order(Runtime::tokio().unwrap());
+        fail_fast(Runtime::tokio().unwrap());
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol() {
+        completion_order(Runtime::smol());
+        fail_fast(Runtime::smol());
+    }
+}
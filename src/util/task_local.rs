@@ -0,0 +1,332 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Declare new task-local keys of type `async_rs::util::LocalKey`.
+///
+/// The values are attached to a future with `LocalKey::scope` and survive across `.await` points
+/// whichever executor drives the future.
+///
+/// ```rust
+/// use async_rs::{Runtime, task_local, traits::*};
+///
+/// task_local! {
+///     static TRACE_ID: u64;
+/// }
+///
+/// let rt = Runtime::noop();
+/// let id = rt.block_on(TRACE_ID.scope(42, async {
+///     std::future::ready(()).await;
+///     TRACE_ID.get()
+/// }));
+/// assert_eq!(id, 42);
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => {
+        $crate::task_local!($(#[$attr])* $vis static $name: $t);
+        $crate::task_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty) => {
+        $(#[$attr])*
+        $vis static $name: $crate::util::LocalKey<$t> = {
+            ::std::thread_local! {
+                static __KEY: ::std::cell::RefCell<::std::option::Option<$t>> =
+                    const { ::std::cell::RefCell::new(::std::option::Option::None) };
+            }
+            $crate::util::LocalKey::__new(__KEY)
+        };
+    };
+}
+
+/// A key for task-local data, declared with `task_local!`
+pub struct LocalKey<T: 'static> {
+    inner: std::thread::LocalKey<RefCell<Option<T>>>,
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn __new(inner: std::thread::LocalKey<RefCell<Option<T>>>) -> Self {
+        Self { inner }
+    }
+
+    /// Run the given future with this key set to `value`.
+    ///
+    /// The value is only visible while the returned future is being polled or dropped.
+    pub fn scope<F: Future>(&'static self, value: T, f: F) -> TaskLocalFuture<T, F> {
+        TaskLocalFuture {
+            key: self,
+            slot: Some(value),
+            future: Some(Box::pin(f)),
+        }
+    }
+
+    /// Access the current value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a `scope` for this key.
+    pub fn with<R, F: FnOnce(&T) -> R>(&'static self, f: F) -> R {
+        self.try_with(f)
+            .expect("cannot access a task-local value outside of its scope")
+    }
+
+    /// Access the current value, failing if called outside of a `scope` for this key.
+    pub fn try_with<R, F: FnOnce(&T) -> R>(&'static self, f: F) -> Result<R, AccessError> {
+        self.inner
+            .try_with(|cell| cell.borrow().as_ref().map(f))
+            .ok()
+            .flatten()
+            .ok_or(AccessError)
+    }
+
+    /// Get a copy of the current value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a `scope` for this key.
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    fn swap(&'static self, slot: &mut Option<T>) {
+        self.inner
+            .with(|cell| mem::swap(&mut *cell.borrow_mut(), slot));
+    }
+}
+
+impl<T: 'static> fmt::Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalKey").finish_non_exhaustive()
+    }
+}
+
+/// The error returned when accessing a task-local value outside of its scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessError;
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("task-local value accessed outside of its scope")
+    }
+}
+
+impl Error for AccessError {}
+
+// Puts the value back in its slot even if the inner future panics
+struct Entered<'a, T: 'static> {
+    key: &'static LocalKey<T>,
+    slot: &'a mut Option<T>,
+}
+
+impl<'a, T: 'static> Entered<'a, T> {
+    fn new(key: &'static LocalKey<T>, slot: &'a mut Option<T>) -> Self {
+        key.swap(slot);
+        Self { key, slot }
+    }
+}
+
+impl<T: 'static> Drop for Entered<'_, T> {
+    fn drop(&mut self) {
+        self.key.swap(self.slot);
+    }
+}
+
+/// A future with a task-local value attached, returned by `LocalKey::scope`
+pub struct TaskLocalFuture<T: 'static, F: Future> {
+    key: &'static LocalKey<T>,
+    slot: Option<T>,
+    future: Option<Pin<Box<F>>>,
+}
+
+impl<T: 'static, F: Future> Unpin for TaskLocalFuture<T, F> {}
+
+impl<T: 'static, F: Future> Future for TaskLocalFuture<T, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let _entered = Entered::new(this.key, &mut this.slot);
+        this.future
+            .as_mut()
+            .expect("TaskLocalFuture polled after completion")
+            .as_mut()
+            .poll(cx)
+    }
+}
+
+impl<T: 'static, F: Future> Drop for TaskLocalFuture<T, F> {
+    fn drop(&mut self) {
+        // Keep the value visible to the inner future's destructors
+        if let Some(future) = self.future.take() {
+            let _entered = Entered::new(self.key, &mut self.slot);
+            drop(future);
+        }
+    }
+}
+
+impl<T: 'static, F: Future> fmt::Debug for TaskLocalFuture<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocalFuture")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A task-local key whose value can be copied into child tasks, see
+/// `Executor::spawn_inheriting`
+pub trait InheritTaskLocal: Sync {
+    /// Store a copy of the current value, if any, into `locals`
+    fn capture(&'static self, locals: &mut TaskLocals);
+}
+
+impl<T: Clone + Send + 'static> InheritTaskLocal for LocalKey<T> {
+    fn capture(&'static self, locals: &mut TaskLocals) {
+        if let Ok(value) = self.try_with(T::clone) {
+            locals.0.push(Box::new(Captured {
+                key: self,
+                slot: Some(value),
+            }));
+        }
+    }
+}
+
+trait Swap: Send {
+    fn swap(&mut self);
+}
+
+struct Captured<T: 'static> {
+    key: &'static LocalKey<T>,
+    slot: Option<T>,
+}
+
+impl<T: Send + 'static> Swap for Captured<T> {
+    fn swap(&mut self) {
+        self.key.swap(&mut self.slot);
+    }
+}
+
+/// A snapshot of task-local values which can be attached to another future
+#[derive(Default)]
+pub struct TaskLocals(Vec<Box<dyn Swap>>);
+
+impl TaskLocals {
+    /// Copy the current values of the given keys, skipping the ones which aren't set
+    pub fn capture(keys: &[&'static dyn InheritTaskLocal]) -> Self {
+        let mut locals = Self::default();
+        for key in keys {
+            key.capture(&mut locals);
+        }
+        locals
+    }
+
+    /// Run the given future with the captured values set
+    pub fn scope<F: Future>(self, f: F) -> TaskLocalsFuture<F> {
+        TaskLocalsFuture {
+            locals: self,
+            future: Some(Box::pin(f)),
+        }
+    }
+
+    fn enter(&mut self) -> EnteredLocals<'_> {
+        self.0.iter_mut().for_each(|local| local.swap());
+        EnteredLocals(self)
+    }
+}
+
+impl fmt::Debug for TaskLocals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocals")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+struct EnteredLocals<'a>(&'a mut TaskLocals);
+
+impl Drop for EnteredLocals<'_> {
+    fn drop(&mut self) {
+        self.0.0.iter_mut().rev().for_each(|local| local.swap());
+    }
+}
+
+/// A future with a snapshot of task-local values attached, returned by `TaskLocals::scope`
+pub struct TaskLocalsFuture<F: Future> {
+    locals: TaskLocals,
+    future: Option<Pin<Box<F>>>,
+}
+
+impl<F: Future> Future for TaskLocalsFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let _entered = this.locals.enter();
+        this.future
+            .as_mut()
+            .expect("TaskLocalsFuture polled after completion")
+            .as_mut()
+            .poll(cx)
+    }
+}
+
+impl<F: Future> Drop for TaskLocalsFuture<F> {
+    fn drop(&mut self) {
+        if let Some(future) = self.future.take() {
+            let _entered = self.locals.enter();
+            drop(future);
+        }
+    }
+}
+
+impl<F: Future> fmt::Debug for TaskLocalsFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocalsFuture")
+            .field("locals", &self.locals)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::simple_block_on;
+    use std::future;
+
+    crate::task_local! {
+        static NAME: &'static str;
+        static DEPTH: u32;
+    }
+
+    #[test]
+    fn nested_scopes() {
+        assert!(NAME.try_with(|_| ()).is_err());
+        simple_block_on(NAME.scope("outer", async {
+            future::ready(()).await;
+            assert_eq!(NAME.get(), "outer");
+            NAME.scope("inner", async {
+                assert_eq!(NAME.get(), "inner");
+            })
+            .await;
+            assert_eq!(NAME.get(), "outer");
+        }));
+        assert!(NAME.try_with(|_| ()).is_err());
+    }
+
+    #[test]
+    fn capture() {
+        let locals =
+            simple_block_on(DEPTH.scope(3, async { super::TaskLocals::capture(&[&NAME, &DEPTH]) }));
+        assert_eq!(simple_block_on(locals.scope(async { DEPTH.get() })), 3);
+        assert!(DEPTH.try_with(|_| ()).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use futures_core::Stream;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{Condvar, Mutex, MutexGuard, OnceLock},
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Timers waiting for their deadline, by deadline then by id, woken up by a dedicated thread
+struct Timers {
+    state: Mutex<TimersState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct TimersState {
+    entries: BTreeMap<(Instant, u64), Waker>,
+    next_id: u64,
+}
+
+// Started on first use
+fn timers() -> &'static Timers {
+    static TIMERS: OnceLock<Timers> = OnceLock::new();
+    TIMERS.get_or_init(|| {
+        thread::Builder::new()
+            .name("async-rs-timer".into())
+            .spawn(|| timers().run())
+            .expect("failed to start the timer thread");
+        Timers {
+            state: Mutex::default(),
+            changed: Condvar::new(),
+        }
+    })
+}
+
+impl Timers {
+    fn lock(&self) -> MutexGuard<'_, TimersState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run(&self) {
+        let mut state = self.lock();
+        loop {
+            let now = Instant::now();
+            let mut expired = Vec::new();
+            while let Some(entry) = state.entries.first_entry()
+                && entry.key().0 <= now
+            {
+                expired.push(entry.remove());
+            }
+            if !expired.is_empty() {
+                // Don't hold the lock while waking tasks up, they may register new timers
+                drop(state);
+                expired.into_iter().for_each(Waker::wake);
+                state = self.lock();
+                continue;
+            }
+            state = match state.entries.first_key_value() {
+                Some(((deadline, _), _)) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.changed
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.changed.wait(state).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+/// A future completing at a given deadline, driven by a dedicated thread.
+///
+/// This only needs `std`, it is used by the builtin runtime.
+#[derive(Debug)]
+pub struct Timer {
+    deadline: Instant,
+    id: Option<u64>,
+}
+
+impl Timer {
+    /// Create a timer completing after the given duration
+    pub fn after(dur: Duration) -> Self {
+        Self::at(Instant::now() + dur)
+    }
+
+    /// Create a timer completing at the given deadline
+    pub fn at(deadline: Instant) -> Self {
+        Self { deadline, id: None }
+    }
+
+    /// The deadline of this timer
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    // Move the deadline, forgetting the previous registration
+    fn reset(&mut self, deadline: Instant) {
+        self.unregister();
+        self.deadline = deadline;
+    }
+
+    fn unregister(&mut self) {
+        if let Some(id) = self.id.take() {
+            timers().lock().entries.remove(&(self.deadline, id));
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = Instant;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            self.unregister();
+            return Poll::Ready(self.deadline);
+        }
+        let timers = timers();
+        let mut state = timers.lock();
+        let id = *self.id.get_or_insert_with(|| {
+            state.next_id += 1;
+            state.next_id
+        });
+        let key = (self.deadline, id);
+        // The entry may be gone if the timer thread woke us up already, register again then
+        state.entries.insert(key, cx.waker().clone());
+        if state
+            .entries
+            .first_key_value()
+            .is_some_and(|(first, _)| *first == key)
+        {
+            timers.changed.notify_one();
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Create a stream yielding every given interval, starting after one interval
+pub fn interval(dur: Duration) -> Interval {
+    Interval {
+        timer: Timer::after(dur),
+        period: dur,
+    }
+}
+
+/// Stream returned by `interval`
+#[derive(Debug)]
+pub struct Interval {
+    timer: Timer,
+    period: Duration,
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let tick = std::task::ready!(Pin::new(&mut self.timer).poll(cx));
+        // Skip the ticks we missed rather than yielding them in a burst
+        let next = (tick + self.period).max(Instant::now());
+        self.timer.reset(next);
+        Poll::Ready(Some(tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::simple_block_on;
+
+    #[test]
+    fn timer() {
+        let start = Instant::now();
+        simple_block_on(async {
+            let (short, long) = (
+                Timer::after(Duration::from_millis(5)),
+                Timer::after(Duration::from_millis(20)),
+            );
+            assert!(long.await > short.await);
+        });
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -1,16 +1,152 @@
-use std::{future::Future, io};
-use tokio::runtime::Handle;
+use crate::util::reentrant_block_on;
+use std::{
+    cell::Cell,
+    future::{self, Future},
+    io,
+    pin::pin,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+
+// The runtime serving block_on_tokio outside of tokio contexts, created on first use
+static BRIDGE_RUNTIME: Mutex<Option<Arc<Runtime>>> = Mutex::new(None);
+
+thread_local! {
+    // Set while polling futures driven by the LocalSet of `Tokio::block_on`, where `spawn_local`
+    // can be used and `block_in_place` is forbidden
+    static IN_LOCAL_SET: Cell<bool> = const { Cell::new(false) };
+}
 
 /// Check whether we're in a tokio context or not
 pub fn inside_tokio() -> bool {
     Handle::try_current().is_ok()
 }
 
-/// Block on the given future in a tokio context, creating a new one if required
+/// Block on the given future in a tokio context.
+///
+/// Outside of tokio contexts, this uses a shared background runtime, see `set_bridge_runtime`.
+/// From within a tokio multi-threaded runtime, the current worker is turned into a blocking
+/// thread with `tokio::task::block_in_place`.
+///
+/// # Panics
+///
+/// Panics if called from an asynchronous context where `block_in_place` cannot be used, such as
+/// a current-thread runtime, see `reentrant_block_on_tokio` for those.
 pub fn block_on_tokio<T>(fut: impl Future<Output = io::Result<T>>) -> io::Result<T> {
-    if let Ok(handle) = Handle::try_current() {
-        handle.block_on(fut)
+    match Handle::try_current() {
+        Ok(handle) if can_block_in_place(&handle) => {
+            tokio::task::block_in_place(move || handle.block_on(fut))
+        }
+        Ok(handle) => handle.block_on(fut),
+        Err(_) => bridge_runtime()?.block_on(fut),
+    }
+}
+
+/// Block on the given future in a tokio context, even from within an asynchronous context.
+///
+/// This behaves like `block_on_tokio`, except that nested calls are handed off to a helper
+/// thread when needed, see `reentrant_block_on` for the cost.
+pub fn reentrant_block_on_tokio<T: Send>(
+    fut: impl Future<Output = io::Result<T>> + Send,
+) -> io::Result<T> {
+    if inside_tokio() {
+        reentrant_block_on(fut)
     } else {
-        tokio::runtime::Runtime::new()?.block_on(fut)
+        bridge_runtime()?.block_on(fut)
+    }
+}
+
+fn lock_bridge() -> MutexGuard<'static, Option<Arc<Runtime>>> {
+    BRIDGE_RUNTIME.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn bridge_runtime() -> io::Result<Arc<Runtime>> {
+    let mut bridge = lock_bridge();
+    if let Some(runtime) = bridge.as_ref() {
+        return Ok(runtime.clone());
+    }
+    let runtime = Arc::new(
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("async-rs-bridge")
+            .enable_all()
+            .build()?,
+    );
+    *bridge = Some(runtime.clone());
+    Ok(runtime)
+}
+
+/// Use the given tokio runtime to serve `block_on_tokio` calls made outside of tokio contexts.
+///
+/// By default, a multi-threaded runtime with a single worker gets created on first use. The
+/// previous runtime, if any, gets shut down once its running calls complete.
+pub fn set_bridge_runtime(runtime: Runtime) {
+    let previous = lock_bridge().replace(Arc::new(runtime));
+    release_bridge(previous);
+}
+
+/// Shut down the runtime serving `block_on_tokio` calls made outside of tokio contexts.
+///
+/// Running calls complete first, a new runtime gets created if `block_on_tokio` is called again.
+pub fn shutdown_bridge_runtime() {
+    let previous = lock_bridge().take();
+    release_bridge(previous);
+}
+
+fn release_bridge(runtime: Option<Arc<Runtime>>) {
+    // If calls are still running, the runtime gets dropped by the last one
+    if let Some(runtime) = runtime.and_then(|runtime| Arc::try_unwrap(runtime).ok()) {
+        runtime.shutdown_background();
+    }
+}
+
+/// Check whether `tokio::task::block_in_place` can be used from here
+pub(crate) fn can_block_in_place(handle: &Handle) -> bool {
+    handle.runtime_flavor() == RuntimeFlavor::MultiThread && !IN_LOCAL_SET.get()
+}
+
+/// Check whether `tokio::task::spawn_local` can be used from here
+pub(crate) fn in_local_set() -> bool {
+    IN_LOCAL_SET.get()
+}
+
+/// Wrap a future driven by a LocalSet, marking the current thread as such while polling it
+pub(crate) fn in_local_set_scope<F: Future>(f: F) -> impl Future<Output = F::Output> {
+    async move {
+        let mut f = pin!(f);
+        future::poll_fn(move |cx| {
+            let _guard = LocalSetGuard(IN_LOCAL_SET.replace(true));
+            f.as_mut().poll(cx)
+        })
+        .await
+    }
+}
+
+// Restores the previous state once dropped
+struct LocalSetGuard(bool);
+
+impl Drop for LocalSetGuard {
+    fn drop(&mut self) {
+        IN_LOCAL_SET.set(self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_runtime() {
+        let value = block_on_tokio(async { Ok(tokio::task::spawn(async { 42 }).await?) });
+        assert_eq!(value.unwrap(), 42);
+        let first = super::bridge_runtime().unwrap();
+        assert!(Arc::ptr_eq(&first, &super::bridge_runtime().unwrap()));
+        shutdown_bridge_runtime();
+        assert!(!Arc::ptr_eq(&first, &super::bridge_runtime().unwrap()));
+        assert_eq!(block_on_tokio(async { Ok(1) }).unwrap(), 1);
+        // Non-Send futures are fine as long as nothing needs to be moved to another thread
+        let local = std::rc::Rc::new(2);
+        assert_eq!(block_on_tokio(async { Ok(*local) }).unwrap(), 2);
+        assert_eq!(reentrant_block_on_tokio(async { Ok(3) }).unwrap(), 3);
     }
 }
@@ -0,0 +1,73 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The number of calls to `consume_budget` between two yields
+pub const BUDGET: u32 = 128;
+
+thread_local! {
+    static BUDGET_LEFT: Cell<u32> = const { Cell::new(BUDGET) };
+}
+
+/// Yield back to the executor once, letting the other tasks run
+pub fn yield_now() -> YieldNow {
+    YieldNow(false)
+}
+
+/// Future returned by `yield_now`
+#[derive(Debug)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Consume a unit of budget, yielding back to the executor once every `BUDGET` calls.
+///
+/// The budget is shared by all the tasks running on the current thread.
+pub async fn consume_budget() {
+    let exhausted = BUDGET_LEFT.with(|left| match left.get() {
+        0 | 1 => {
+            left.set(BUDGET);
+            true
+        }
+        n => {
+            left.set(n - 1);
+            false
+        }
+    });
+    if exhausted {
+        yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{pin::pin, task::Waker};
+
+    #[test]
+    fn consume_budget() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut yields = 0;
+        for _ in 0..BUDGET * 2 {
+            let mut budget = pin!(super::consume_budget());
+            while budget.as_mut().poll(&mut cx).is_pending() {
+                yields += 1;
+            }
+        }
+        assert_eq!(yields, 2);
+    }
+}